@@ -0,0 +1,197 @@
+//! Embedding of local assets into the generated HTML as data URIs.
+
+use std::fs;
+use std::path::Path;
+
+use crate::utils::{mime_type, percent_decode};
+
+/// The default maximum size of an asset to be embedded, in bytes.
+pub const DEFAULT_EMBED_MAX_SIZE: u64 = 1024 * 1024;
+
+/// Embed the assets referenced by `src` attributes in the HTML as data URIs.
+/// Relative URLs are resolved against the base directory, and only the files within the root directory are embedded.
+/// Missing files, files outside the root and files larger than `max_size` bytes are left as they are with a warning.
+pub fn embed_html_assets(html: &str, base: &Path, root: &Path, max_size: u64) -> String {
+    let mut res = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(pos) = find_attr(rest, "src") {
+        // position right after the opening quote
+        let start = pos + 5;
+        let quote = rest.as_bytes()[pos + 4] as char;
+
+        res.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let end = match rest.find(quote) {
+            Some(end) => end,
+            None => break,
+        };
+
+        let url = &rest[..end];
+        match embed_asset(url, base, root, max_size) {
+            Some(data) => res.push_str(&data),
+            None => res.push_str(url),
+        }
+
+        rest = &rest[end..];
+    }
+
+    res.push_str(rest);
+    res
+}
+
+/// Find the next quoted attribute with the name, such as `src`.
+pub(crate) fn find_attr(html: &str, name: &str) -> Option<usize> {
+    let bytes = html.as_bytes();
+    let pattern = format!("{}=", name);
+    let mut from = 0;
+
+    while let Some(pos) = html[from..].find(&pattern).map(|p| p + from) {
+        let preceded_by_space = pos > 0 && bytes[pos - 1].is_ascii_whitespace();
+        let quoted = bytes
+            .get(pos + pattern.len())
+            .map(|&b| b == b'"' || b == b'\'')
+            .unwrap_or(false);
+
+        if preceded_by_space && quoted {
+            return Some(pos);
+        }
+
+        from = pos + pattern.len();
+    }
+
+    None
+}
+
+/// Determine whether the URL refers to a local file.
+pub(crate) fn is_local(url: &str) -> bool {
+    if url.is_empty() || url.starts_with('#') || url.starts_with("//") {
+        return false;
+    }
+
+    // anything with a scheme such as "https:" or "data:" is not local
+    match url.find(':') {
+        Some(pos) => url[..pos].contains('/'),
+        None => true,
+    }
+}
+
+/// Read the local asset referenced by the URL and encode it as a data URI.
+/// Returns `None` if the URL is not local or the asset can not be embedded.
+/// Assets outside the root directory are never embedded, so that absolute paths or `../` paths
+/// can not publish any other local file.
+pub fn embed_asset(url: &str, base: &Path, root: &Path, max_size: u64) -> Option<String> {
+    if !is_local(url) {
+        return None;
+    }
+
+    let file = url.split(['?', '#']).next().unwrap_or_default();
+    let joined = base.join(percent_decode(file));
+
+    let path = match joined.canonicalize() {
+        Ok(path) => path,
+        Err(_) => {
+            warn!("could not find asset to embed: {}", joined.display());
+            return None;
+        }
+    };
+
+    if !root.canonicalize().is_ok_and(|root| path.starts_with(root)) {
+        warn!(
+            "asset is outside the root directory and will not be embedded: {}",
+            joined.display()
+        );
+        return None;
+    }
+
+    let size = match fs::metadata(&path) {
+        Ok(meta) if meta.is_file() => meta.len(),
+        _ => {
+            warn!("could not find asset to embed: {}", path.display());
+            return None;
+        }
+    };
+
+    if size > max_size {
+        warn!(
+            "asset is larger than {} bytes and will not be embedded: {}",
+            max_size,
+            path.display()
+        );
+        return None;
+    }
+
+    match fs::read(&path) {
+        Ok(bytes) => {
+            debug!("embedding asset: {}", path.display());
+
+            let mime = mime_type(&path);
+            let mime = mime.split(';').next().unwrap_or(mime);
+            Some(format!("data:{};base64,{}", mime, base64::encode(bytes)))
+        }
+        Err(err) => {
+            warn!("could not read asset: {}. {}", path.display(), err);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embed_html_assets() {
+        let base = Path::new("docs/static");
+        let root = Path::new("docs");
+
+        let html = embed_html_assets(
+            r#"<p><img src="favicon.ico" alt="icon"></p>"#,
+            base,
+            root,
+            DEFAULT_EMBED_MAX_SIZE,
+        );
+        assert!(html.starts_with(r#"<p><img src="data:image/x-icon;base64,"#));
+        assert!(html.ends_with(r#"" alt="icon"></p>"#));
+
+        // single quotes
+        let html = embed_html_assets(
+            "<img src='favicon.ico'>",
+            base,
+            root,
+            DEFAULT_EMBED_MAX_SIZE,
+        );
+        assert!(html.starts_with("<img src='data:image/x-icon;base64,"));
+        assert!(html.ends_with("'>"));
+
+        // remote, missing, too large and non attribute text are left alone
+        let html = r#"<img src="https://example.com/a.png"><img src="missing.png"><img src="logo-192x192.png"><p>src="favicon.ico"</p>"#;
+        assert_eq!(embed_html_assets(html, base, root, 1024), html);
+
+        // absolute and relative paths outside the root are not embedded
+        let cargo = Path::new("Cargo.toml").canonicalize().unwrap();
+        let html = format!(
+            r#"<img src="{}"><img src="../../Cargo.toml"><img src="../../../../../etc/passwd">"#,
+            cargo.display()
+        );
+        assert_eq!(
+            embed_html_assets(&html, base, root, DEFAULT_EMBED_MAX_SIZE),
+            html
+        );
+        assert!(embed_asset("../static/favicon.ico", base, root, DEFAULT_EMBED_MAX_SIZE).is_some());
+    }
+
+    #[test]
+    fn test_is_local() {
+        assert!(is_local("image.png"));
+        assert!(is_local("./images/image.png"));
+        assert!(is_local("../image.png?v=1"));
+        assert!(is_local("images/a:b.png"));
+        assert!(!is_local(""));
+        assert!(!is_local("#top"));
+        assert!(!is_local("//example.com/image.png"));
+        assert!(!is_local("https://example.com/image.png"));
+        assert!(!is_local("data:image/png;base64,AAAA"));
+    }
+}
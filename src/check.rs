@@ -0,0 +1,280 @@
+//! Checks of the documentation sources reporting all the problems found instead of rendering.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use comrak::nodes::NodeValue;
+use comrak::{parse_document, Arena, ComrakOptions};
+use serde_json::Value;
+
+use crate::assets::is_local;
+use crate::config::merge_layers;
+use crate::content::Content;
+use crate::data::{find_config, read_config, resolve_root, Data, LinkType};
+use crate::error::{Error, Result};
+use crate::utils::{is_ext, percent_decode, resolve_path};
+
+/// A problem found in the documentation sources.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    /// The file the problem was found in, either the config file or a content file.
+    pub path: Option<PathBuf>,
+    /// The description of the problem.
+    pub message: String,
+}
+
+impl Problem {
+    fn new(path: Option<&Path>, message: String) -> Problem {
+        Problem {
+            path: path.map(Path::to_path_buf),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.path.as_ref() {
+            Some(path) => write!(f, "{}: {}", path.display(), self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Check the documentation in the root directory and return all the problems found.
+/// Errors are only returned when the sources can not be checked at all,
+/// such as when the config file can not be parsed.
+pub fn check(root: &Path, initial_value: Option<Data>) -> Result<Vec<Problem>> {
+    let r = resolve_root(root)?;
+    let mut problems = Vec::new();
+    let initial = initial_value.unwrap_or_default();
+
+    let file = find_config(&r, &initial)?;
+    let mut config = None;
+    if let Some(file_path) = file.as_ref() {
+        let mut value: Value = read_config(file_path)?;
+        check_config(&r, file_path, &mut value, &mut problems);
+
+        config = Some(serde_json::from_value(value).map_err(|err| Error::Config {
+            path: file_path.clone(),
+            source: Box::new(err),
+        })?);
+    }
+
+    let mut data = merge_layers(config, initial, file)?.data;
+
+    // the problems that stop the build are reported as the only remaining problem
+    if let Err(err) = data.build(&r) {
+        problems.push(Problem::new(err.path(), err.to_string()));
+        return Ok(problems);
+    }
+
+    check_labels(&data, &mut problems);
+    check_links(&r, &data, &mut problems);
+
+    Ok(problems)
+}
+
+/// Check the files, directories and links referenced by the config, removing the invalid entries
+/// so the rest of the documentation can still be built and checked.
+fn check_config(root: &Path, config: &Path, value: &mut Value, problems: &mut Vec<Problem>) {
+    let exists = |path: &str| resolve_path(root, Path::new(path)).is_ok();
+
+    for key in &["main", "header", "footer"] {
+        let missing = value
+            .get(key)
+            .and_then(|c| c.get("file"))
+            .and_then(Value::as_str)
+            .filter(|file| !exists(file));
+
+        if let Some(file) = missing {
+            let message = format!("{}: file not found: {}", key, file);
+            problems.push(Problem::new(Some(config), message));
+            value[key] = Value::Null;
+        }
+    }
+
+    if let Some(contents) = value.get_mut("contents").and_then(Value::as_array_mut) {
+        let mut index = 0;
+        contents.retain(|c| {
+            let mut keep = true;
+            if c.get("is_heading") == Some(&Value::Bool(true))
+                && c.get("label")
+                    .and_then(Value::as_str)
+                    .is_none_or(|l| l.trim().is_empty())
+            {
+                let message = format!("contents[{}]: heading without a label", index);
+                problems.push(Problem::new(Some(config), message));
+            }
+            for key in &["file", "dir"] {
+                if let Some(path) = c.get(key).and_then(Value::as_str).filter(|p| !exists(p)) {
+                    let message = format!("contents[{}]: {} not found: {}", index, key, path);
+                    problems.push(Problem::new(Some(config), message));
+                    keep = false;
+                }
+            }
+            index += 1;
+            keep
+        });
+    }
+
+    if let Some(links) = value.get_mut("links").and_then(Value::as_array_mut) {
+        for (index, link) in links.iter_mut().enumerate() {
+            let link_type = match link.get("link_type") {
+                Some(Value::Null) | None => continue,
+                Some(link_type) => link_type,
+            };
+
+            if link_type
+                .as_str()
+                .and_then(|t| t.parse::<LinkType>().ok())
+                .is_none()
+            {
+                let message = format!("links[{}]: unknown link_type: {}", index, link_type);
+                problems.push(Problem::new(Some(config), message));
+                link["link_type"] = Value::Null;
+            }
+        }
+    }
+}
+
+/// Check for contents with the same label, as the labels are used as the anchors of the contents.
+fn check_labels(data: &Data, problems: &mut Vec<Problem>) {
+    let mut labels = HashSet::new();
+
+    for c in data.contents.iter().flatten() {
+        if c.is_heading == Some(true) || c.is_break == Some(true) || c.url.is_some() {
+            continue;
+        }
+
+        if let Some(label) = c.label.as_deref() {
+            if !labels.insert(label) {
+                let message = format!("duplicate label: {}", label);
+                problems.push(Problem::new(c.file.as_deref(), message));
+            }
+        }
+    }
+}
+
+/// Check the relative links of the markdown contents, reporting the links to files that do not
+/// exist and to markdown files that are not part of the documentation.
+fn check_links(root: &Path, data: &Data, problems: &mut Vec<Problem>) {
+    let all = || {
+        data.main
+            .iter()
+            .chain(data.header.iter())
+            .chain(data.footer.iter())
+            .chain(data.contents.iter().flatten())
+    };
+
+    let file_path = |c: &Content| {
+        c.file
+            .as_ref()
+            .and_then(|file| resolve_path(root, file).ok())
+    };
+
+    let doc_files: HashSet<PathBuf> = all().filter_map(file_path).collect();
+    let mut checked = HashSet::new();
+
+    for c in all() {
+        let markdown = match c.markdown.as_ref() {
+            Some(markdown) => markdown,
+            None => continue,
+        };
+
+        // the same file can be used for multiple contents, such as the main content
+        let path = file_path(c);
+        if let Some(path) = path.as_ref() {
+            if !checked.insert(path.clone()) {
+                continue;
+            }
+        }
+        let base = path
+            .as_ref()
+            .and_then(|p| p.parent())
+            .unwrap_or(root)
+            .to_path_buf();
+
+        for url in markdown_links(markdown) {
+            if !is_local(&url) {
+                continue;
+            }
+
+            let target = url.split(['#', '?']).next().unwrap_or_default();
+            if target.is_empty() {
+                continue;
+            }
+
+            // absolute URLs are relative to the root of the site
+            let target = match target.strip_prefix('/') {
+                Some(target) => root.join(percent_decode(target)),
+                None => base.join(percent_decode(target)),
+            };
+            let message = match target.canonicalize() {
+                Err(_) => format!("broken link: {}", url),
+                Ok(target) if is_ext(&target, "md") && !doc_files.contains(&target) => {
+                    format!("link to a file outside the documentation: {}", url)
+                }
+                Ok(_) => continue,
+            };
+
+            problems.push(Problem::new(path.as_deref(), message));
+        }
+    }
+}
+
+/// Collect the URLs of all the links and images in the markdown.
+fn markdown_links(markdown: &str) -> Vec<String> {
+    let arena = Arena::new();
+    let options = ComrakOptions {
+        ext_autolink: true,
+        ..ComrakOptions::default()
+    };
+    let root = parse_document(&arena, markdown, &options);
+
+    root.descendants()
+        .filter_map(|node| match node.data.borrow().value {
+            NodeValue::Link(ref link) | NodeValue::Image(ref link) => {
+                Some(String::from_utf8_lossy(&link.url).into_owned())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_links() {
+        assert_eq!(
+            markdown_links("[a](a.md) ![b](img/b.png)\n\n<https://example.com> [c](#c)"),
+            vec!["a.md", "img/b.png", "https://example.com", "#c"]
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        let problems = check(Path::new("tests/fixtures/check"), None).unwrap();
+        let messages: Vec<&str> = problems.iter().map(|p| p.message.as_str()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "contents[2]: file not found: missing.md",
+                "contents[3]: dir not found: missing_dir",
+                "contents[4]: heading without a label",
+                "links[1]: unknown link_type: \"font\"",
+                "duplicate label: Intro",
+                "broken link: nope.md",
+                "link to a file outside the documentation: extra.md#usage",
+                "broken link: images/diagram%20v2.png",
+            ]
+        );
+
+        assert!(problems[0].path.as_ref().unwrap().ends_with("mdpage.json"));
+        assert!(problems[6].path.as_ref().unwrap().ends_with("links.md"));
+    }
+}
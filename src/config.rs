@@ -0,0 +1,297 @@
+//! Layered configuration, merging the built-in defaults, the config file,
+//! the environment and the command line options.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::data::{find_config, read_config, resolve_root, Data};
+use crate::error::{Error, Result};
+
+/// The prefix of the environment variables setting the config properties, such as `MDPAGE_TITLE`.
+pub const ENV_PREFIX: &str = "MDPAGE_";
+
+/// The layers of the configuration, from the lowest to the highest precedence.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Layer {
+    /// The built-in defaults.
+    Default,
+    /// The config file.
+    Config,
+    /// The `MDPAGE_*` environment variables.
+    Env,
+    /// The command line options, or the initial data of the build.
+    Cli,
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Layer::Default => "default",
+            Layer::Config => "config",
+            Layer::Env => "env",
+            Layer::Cli => "cli",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The configuration resolved from all the layers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    /// The merged data.
+    pub data: Data,
+    /// The config file that was read, if any.
+    pub file: Option<PathBuf>,
+    /// The layer that supplied each of the set properties.
+    pub sources: BTreeMap<String, Layer>,
+}
+
+/// Prints each of the set properties as `name = value (layer)`, with the value in JSON.
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let props = to_map(&self.data);
+
+        for (name, layer) in self.sources.iter() {
+            if let Some(value) = props.get(name) {
+                writeln!(f, "{} = {} ({})", name, value, layer)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Resolve the configuration of the root directory. The properties are merged one by one, with each layer
+/// overriding the ones below it: the built-in defaults, the config file, the `MDPAGE_*` environment variables,
+/// and the initial value, which holds the command line options.
+/// The config file takes the place of the defaults, so these only apply when there is no config file.
+/// The properties of the initial value left at their default, as in `Data::default()`, do not override the other layers.
+pub fn resolve_config(root: &Path, initial_value: Option<Data>) -> Result<Config> {
+    let r = resolve_root(root)?;
+    let initial = initial_value.unwrap_or_default();
+
+    let file = find_config(&r, &initial)?;
+    let config = match file.as_ref() {
+        Some(path) => Some(read_config(path)?),
+        None => None,
+    };
+
+    merge_layers(config, initial, file)
+}
+
+/// Merge the config file data and the initial data with the defaults and the environment.
+pub(crate) fn merge_layers(
+    config: Option<Data>,
+    initial: Data,
+    file: Option<PathBuf>,
+) -> Result<Config> {
+    let env = env_layer(env::vars())?;
+    merge(config, env, initial, file)
+}
+
+fn merge(
+    config: Option<Data>,
+    env: Map<String, Value>,
+    initial: Data,
+    file: Option<PathBuf>,
+) -> Result<Config> {
+    let defaults = to_map(&Data::default());
+
+    let (default_layer, config_layer) = match config.as_ref() {
+        Some(config) => (Map::new(), to_map(config)),
+        None => (defaults.clone(), Map::new()),
+    };
+    let cli_layer = to_map(&initial)
+        .into_iter()
+        .filter(|(name, value)| defaults.get(name) != Some(value))
+        .collect();
+
+    let layers = vec![
+        (Layer::Default, default_layer),
+        (Layer::Config, config_layer),
+        (Layer::Env, env),
+        (Layer::Cli, cli_layer),
+    ];
+
+    let mut merged = Map::new();
+    let mut sources = BTreeMap::new();
+    for (layer, props) in layers {
+        for (name, value) in props {
+            if !value.is_null() {
+                sources.insert(name.clone(), layer);
+                merged.insert(name, value);
+            }
+        }
+    }
+
+    let data = serde_json::from_value(Value::Object(merged)).map_err(|err| Error::Config {
+        path: file.clone().unwrap_or_default(),
+        source: Box::new(err),
+    })?;
+
+    Ok(Config {
+        data,
+        file,
+        sources,
+    })
+}
+
+/// Read the config properties from the environment variables named after them, such as `MDPAGE_FULL_PAGE`.
+/// The values are parsed as JSON, and used as strings otherwise.
+fn env_layer(vars: impl Iterator<Item = (String, String)>) -> Result<Map<String, Value>> {
+    let names = to_map(&Data::empty());
+
+    let mut res = Map::new();
+    for (key, raw) in vars {
+        let name = match key.strip_prefix(ENV_PREFIX) {
+            Some(name) => name.to_lowercase(),
+            None => continue,
+        };
+
+        if !names.contains_key(&name) {
+            warn!("unknown config property in environment variable: {}", key);
+            continue;
+        }
+
+        if let Ok(value) = serde_json::from_str(&raw) {
+            if parse_property(&name, &value).is_ok() {
+                res.insert(name, value);
+                continue;
+            }
+        }
+
+        let value = Value::String(raw);
+        parse_property(&name, &value).map_err(|err| Error::Env {
+            name: key.clone(),
+            source: Box::new(err),
+        })?;
+        res.insert(name, value);
+    }
+
+    Ok(res)
+}
+
+/// Parse the data with only the property set, to check the value is valid for the property.
+fn parse_property(name: &str, value: &Value) -> serde_json::Result<Data> {
+    let mut props = Map::new();
+    props.insert(name.to_string(), value.clone());
+    serde_json::from_value(Value::Object(props))
+}
+
+fn to_map(data: &Data) -> Map<String, Value> {
+    match serde_json::to_value(data) {
+        Ok(Value::Object(props)) => props,
+        _ => Map::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(vars: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<Vec<(String, String)>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn test_env_layer() {
+        let env = env_layer(vars(&[
+            ("MDPAGE_TITLE", "123"),
+            ("MDPAGE_FULL_PAGE", "true"),
+            ("MDPAGE_MAX_DEPTH", "2"),
+            ("MDPAGE_EXCLUDE", r#"["CHANGELOG"]"#),
+            ("MDPAGE_UNKNOWN", "x"),
+            ("HOME", "/root"),
+        ]))
+        .unwrap();
+        assert_eq!(env.len(), 4);
+        assert_eq!(env["title"], Value::from("123"));
+        assert_eq!(env["full_page"], Value::from(true));
+        assert_eq!(env["max_depth"], Value::from(2));
+        assert_eq!(env["exclude"], serde_json::json!(["CHANGELOG"]));
+
+        match env_layer(vars(&[("MDPAGE_MAX_DEPTH", "deep")])) {
+            Err(Error::Env { name, .. }) => assert_eq!(name, "MDPAGE_MAX_DEPTH"),
+            res => panic!("expected env error, got: {:?}", res),
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let config = Data {
+            title: Some(String::from("Config title")),
+            subtitle: Some(String::from("Config subtitle")),
+            full_page: Some(true),
+            toc: Some(true),
+            ..Data::default()
+        };
+        let env = env_layer(vars(&[("MDPAGE_SUBTITLE", "Env subtitle")])).unwrap();
+        let initial = Data {
+            title: Some(String::from("CLI title")),
+            ..Data::default()
+        };
+
+        let config = merge(
+            Some(config),
+            env,
+            initial,
+            Some(PathBuf::from("mdpage.toml")),
+        )
+        .unwrap();
+        assert_eq!(
+            config.data,
+            Data {
+                title: Some(String::from("CLI title")),
+                subtitle: Some(String::from("Env subtitle")),
+                full_page: Some(true),
+                toc: Some(true),
+                ..Data::default()
+            }
+        );
+        assert_eq!(config.sources["title"], Layer::Cli);
+        assert_eq!(config.sources["subtitle"], Layer::Env);
+        assert_eq!(config.sources["full_page"], Layer::Config);
+        assert_eq!(config.sources.len(), 4);
+        assert_eq!(
+            config.to_string(),
+            "full_page = true (config)\nsubtitle = \"Env subtitle\" (env)\ntitle = \"CLI title\" (cli)\ntoc = true (config)\n"
+        );
+
+        // the defaults apply only without a config file
+        let config = merge(None, Map::new(), Data::default(), None).unwrap();
+        assert_eq!(config.data, Data::default());
+        assert_eq!(config.sources["full_page"], Layer::Default);
+
+        let config = merge(
+            Some(Data::empty()),
+            Map::new(),
+            Data::default(),
+            Some(PathBuf::from("mdpage.toml")),
+        )
+        .unwrap();
+        assert_eq!(config.data, Data::empty());
+        assert!(config.sources.is_empty());
+
+        // invalid merged properties are a config error
+        let mut env = Map::new();
+        env.insert(String::from("max_depth"), Value::from("deep"));
+        match merge(
+            None,
+            env,
+            Data::default(),
+            Some(PathBuf::from("mdpage.toml")),
+        ) {
+            Err(Error::Config { path, .. }) => assert_eq!(path, PathBuf::from("mdpage.toml")),
+            res => panic!("expected config error, got: {:?}", res),
+        }
+    }
+}
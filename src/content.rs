@@ -1,17 +1,29 @@
 //! Content struct represents content of the document as well as the menu items.
 
-use std::error::Error;
 use std::fs;
-use std::fs::File;
-use std::io::prelude::*;
 use std::path::Path;
 use std::path::PathBuf;
 
-use comrak::{markdown_to_html, ComrakOptions};
+use comrak::{format_html, markdown_to_html, parse_document, Arena};
 use derivative::Derivative;
 use serde::{Deserialize, Serialize};
-
-use crate::utils::{build_title_for_dir, get_title_from_file, is_ext, is_index_file};
+use serde_json::{Map, Value};
+
+use crate::assets::embed_html_assets;
+use crate::data::DirConfig;
+use crate::error::{Error, Result};
+use crate::filter::PathFilter;
+use crate::front_matter::{parse_front_matter, read_front_matter};
+use crate::highlight::highlight_blocks;
+use crate::markdown::{omit_raw_html, MarkdownOptions};
+use crate::order::{order_contents, order_sections, strip_numeric_prefix, SortOrder};
+use crate::sanitize::sanitize_html;
+use crate::split::SplitLevel;
+use crate::toc::{build_toc, TocEntry};
+use crate::utils::{
+    build_title_for_dir, get_title_from_file, is_ext, is_index_file, read_dir, resolve_path,
+    slugify, title_string,
+};
 
 /// Content struct represents content of the document as well as the menu items.
 #[derive(Serialize, Deserialize, Debug, Clone, Default, Derivative)]
@@ -44,6 +56,42 @@ pub struct Content {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub html: Option<String>,
 
+    /// The table of contents of the second and third level headings in the markdown content.
+    /// This is generated when converting the `markdown` contents if enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toc: Option<Vec<TocEntry>>,
+
+    /// The nesting level of the content in the menu, set for the contents of nested subdirectories.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub level: Option<usize>,
+
+    /// The weight used to order the automatically discovered contents, lower weights come first.
+    /// Contents without weight come after the ones with weight, ordered by file name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weight: Option<i64>,
+
+    /// A boolean flag to leave the content out of the menu.
+    /// The content is still rendered and can be linked to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hidden: Option<bool>,
+
+    /// A boolean flag to mark the content as a draft, which leaves it out of the document entirely.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub draft: Option<bool>,
+
+    /// The heading level to split the markdown at, `h1` or `h2`, into separate contents with their own entries
+    /// in the menu, labeled with the headings. The part before the first heading keeps the label of the content.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub split: Option<SplitLevel>,
+
+    /// The description of the content, used in the metadata of the page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Any custom fields made available to the templates.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extra: Option<Map<String, Value>>,
+
     /// The path to the markdown or HTML file used for the content.
     /// We read this into `markdown` property and convert it to HTML content to be displayed.
     /// If `label` is not specified, we also infer the title for the content from the heading in markdown content.
@@ -75,6 +123,14 @@ impl Content {
             label: None,
             markdown: None,
             html: None,
+            toc: None,
+            level: None,
+            weight: None,
+            hidden: None,
+            draft: None,
+            split: None,
+            description: None,
+            extra: None,
             url: None,
             dir: None,
             file,
@@ -90,6 +146,14 @@ impl Content {
             dir: None,
             markdown: None,
             html: None,
+            toc: None,
+            level: None,
+            weight: None,
+            hidden: None,
+            draft: None,
+            split: None,
+            description: None,
+            extra: None,
             url: None,
         }
     }
@@ -103,74 +167,164 @@ impl Content {
             dir: None,
             markdown: None,
             html: None,
+            toc: None,
+            level: None,
+            weight: None,
+            hidden: None,
+            draft: None,
+            split: None,
+            description: None,
+            extra: None,
             url: None,
         }
     }
 
     /// Initializes the label from the file property if present.
-    pub fn init_from_file(&mut self, root: &Path) {
-        if self.file.is_some() {
-            let mut pathbuf = self.file.clone().unwrap();
-
-            if root.has_root() && pathbuf.is_relative() {
-                pathbuf = root
-                    .join(&pathbuf)
-                    .canonicalize()
-                    .map_err(|err| {
-                        format!(
-                            "could not resolve path. root: {} path: {}. {}",
-                            root.display(),
-                            pathbuf.display(),
-                            err.to_string()
-                        )
-                    })
-                    .unwrap();
-            }
+    /// The label in the front matter of the file takes precedence over the title.
+    pub fn init_from_file(&mut self, root: &Path) -> Result<()> {
+        if let Some(file) = self.file.as_ref() {
+            let pathbuf = resolve_path(root, file)?;
 
             if is_ext(&pathbuf, "md") {
-                self.label = get_title_from_file(&pathbuf, true).unwrap_or_else(|_| {
-                    panic!("could not get title from path: {}", pathbuf.display())
-                });
+                self.label = get_title_from_file(&pathbuf, true)?;
+
+                if let Some(mut fm) = read_front_matter(&pathbuf)? {
+                    if let Some(label) = fm.label.take() {
+                        self.label = Some(label);
+                    }
+                    fm.fill(self);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Initializes the label from the heading of the markdown property if there is no file,
+    /// such as for the markdown read from the standard input. The front matter block is removed from the markdown.
+    pub fn init_from_markdown(&mut self) -> Result<()> {
+        let markdown = match (self.file.as_ref(), self.markdown.as_ref()) {
+            (None, Some(markdown)) => markdown.clone(),
+            _ => return Ok(()),
+        };
+
+        let (fm, rest) = parse_front_matter(&markdown).map_err(|source| Error::FrontMatter {
+            path: PathBuf::from("-"),
+            source,
+        })?;
+
+        if self.label.is_none() {
+            self.label = title_string(rest.as_bytes());
+        }
+        if let Some(mut fm) = fm {
+            if let Some(label) = fm.label.take() {
+                self.label = Some(label);
             }
+            fm.fill(self);
         }
+        self.markdown = Some(rest.trim().to_owned());
+
+        Ok(())
     }
 }
 
+/// Options used when rendering the content into HTML.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderOptions {
+    /// The maximum size of local assets to be embedded as data URIs.
+    /// Assets are not embedded if not set.
+    pub embed_max_size: Option<u64>,
+    /// Whether to generate the table of contents and anchor IDs for the headings.
+    pub toc: bool,
+    /// Whether to highlight the syntax of the fenced code blocks, styled by the stylesheet of the theme.
+    pub highlight: bool,
+    /// The markdown extensions and rendering options.
+    pub markdown: MarkdownOptions,
+    /// Whether to sanitize the rendered HTML, removing scripts and any other unsafe markup.
+    pub safe: bool,
+}
+
+impl RenderOptions {
+    /// Whether the headings get anchor IDs, either for the table of contents or when enabled explicitly.
+    pub fn header_ids(&self) -> bool {
+        self.toc || self.markdown.header_ids == Some(true)
+    }
+}
+
+/// The prefix of the heading IDs of a content, the slug of its label followed by a `-`.
+pub(crate) fn header_id_prefix(label: Option<&str>) -> String {
+    match label.map(slugify) {
+        Some(slug) if !slug.is_empty() => format!("{}-", slug),
+        _ => String::new(),
+    }
+}
+
+/// The maximum depth of the discovered subdirectories when it is not configured.
+pub const DEFAULT_MAX_DEPTH: usize = 1;
+
+/// Options used when discovering the contents in the directories.
+#[derive(Debug, Clone, Default)]
+pub struct DiscoverOptions {
+    /// The maximum depth of the subdirectories to discover, without limit if not set.
+    pub max_depth: Option<usize>,
+    /// The strategy used to order the contents.
+    pub sort: SortOrder,
+    /// The filter of the files and directories to discover.
+    pub filter: PathFilter,
+    /// The heading level to split the discovered contents at, unless set for the content.
+    pub split: Option<SplitLevel>,
+}
+
 /// Fills the content based on the properties.
 /// Does nothing if file or markdown are not present.
 /// If file is present we read it and set markdown or html property to the content as appropriate depending on the file type.
 /// If markdown file initializes the label from the file and convert the content to html and set the property.
-pub fn fill_content(c: &mut Content, root: &Path) -> Result<(), Box<dyn Error>> {
+pub fn fill_content(c: &mut Content, root: &Path, opts: &RenderOptions) -> Result<()> {
     if c.url.is_some() || c.html.is_some() || (c.file.is_none() && c.markdown.is_none()) {
+        // the raw HTML set in the config is sanitized as well
+        if let (true, Some(html)) = (opts.safe, c.html.as_ref()) {
+            c.html = Some(sanitize_html(html));
+        }
         return Ok(());
     }
 
-    if c.file.is_some() {
-        let mut pathbuf = c.file.clone().unwrap();
-
-        if root.has_root() && pathbuf.is_relative() {
-            pathbuf = root
-                .join(&pathbuf)
-                .canonicalize()
-                .map_err(|err| {
-                    format!(
-                        "could not resolve path. root: {} path: {}. {}",
-                        root.display(),
-                        pathbuf.display(),
-                        err.to_string()
-                    )
-                })
-                .unwrap();
-        }
+    // the directory relative assets are resolved against
+    let mut base = root.to_path_buf();
 
+    if let Some(file) = c.file.as_ref() {
+        let pathbuf = resolve_path(root, file)?;
         let path = pathbuf.as_path();
 
-        if is_ext(&path, "md") || is_ext(&path, "html") || is_ext(&path, "htm") {
+        if let Some(parent) = path.parent() {
+            base = root.join(parent);
+        }
+
+        // the markdown already set, such as a section split from the file, is not read again
+        if c.markdown.is_none()
+            && (is_ext(path, "md") || is_ext(path, "html") || is_ext(path, "htm"))
+        {
             info!("processing file: {}", path.display());
 
+            let file_contents =
+                fs::read_to_string(path).map_err(|err| Error::read_file(path, err))?;
+            let mut trimmed = file_contents.trim();
+
+            if is_ext(path, "md") {
+                let (fm, markdown) =
+                    parse_front_matter(trimmed).map_err(|source| Error::FrontMatter {
+                        path: path.to_path_buf(),
+                        source,
+                    })?;
+
+                if let Some(fm) = fm {
+                    fm.fill(c);
+                }
+                trimmed = markdown.trim();
+            }
+
             if c.label.is_none() {
-                if is_ext(&path, "md") {
-                    let title = get_title_from_file(&path, true)?;
+                if is_ext(path, "md") {
+                    let title = get_title_from_file(path, true)?;
                     c.label = title;
                 } else if let Some(name_str) =
                     path.file_stem().and_then(|name_str| name_str.to_str())
@@ -179,26 +333,8 @@ pub fn fill_content(c: &mut Content, root: &Path) -> Result<(), Box<dyn Error>>
                 }
             }
 
-            let mut file_contents = String::new();
-            let mut file = File::open(path).map_err(|err| {
-                format!(
-                    "Error reading file: {}. {}",
-                    path.display(),
-                    err.to_string()
-                )
-            })?;
-
-            file.read_to_string(&mut file_contents).map_err(|err| {
-                format!(
-                    "Error reading file: {}. {}",
-                    path.display(),
-                    err.to_string()
-                )
-            })?;
-            let trimmed = file_contents.trim();
-
             if !trimmed.is_empty() {
-                if is_ext(&path, "md") {
+                if is_ext(path, "md") {
                     c.markdown = Some(trimmed.to_owned());
                 } else {
                     c.html = Some(trimmed.to_owned());
@@ -207,37 +343,100 @@ pub fn fill_content(c: &mut Content, root: &Path) -> Result<(), Box<dyn Error>>
         }
     }
 
-    if c.html.is_none() && c.markdown.is_some() {
-        let options = ComrakOptions {
-            ext_strikethrough: true,
-            ext_autolink: true,
-            ext_table: true,
-            unsafe_: true,
-            ext_tagfilter: true,
-            ext_tasklist: true,
-            ..ComrakOptions::default()
+    if let (None, Some(markdown)) = (&c.html, &c.markdown) {
+        let mut options = opts.markdown.comrak_options();
+
+        if opts.header_ids() {
+            // prefix the IDs with the label so they are unique within the whole document
+            let prefix = header_id_prefix(c.label.as_deref());
+            if opts.toc {
+                c.toc = Some(build_toc(markdown, &options, &prefix));
+            }
+            options.ext_header_ids = Some(prefix);
+        }
+
+        c.html = if opts.highlight {
+            let arena = Arena::new();
+            let root = parse_document(&arena, markdown, &options);
+
+            // the highlighted code is raw HTML, so the raw HTML of the markdown is removed instead
+            if !options.unsafe_ {
+                omit_raw_html(root);
+                options.unsafe_ = true;
+            }
+            highlight_blocks(root);
+
+            let mut html = Vec::new();
+            format_html(root, &options, &mut html).map_err(|source| Error::Output { source })?;
+            Some(String::from_utf8_lossy(&html).into_owned())
+        } else {
+            Some(markdown_to_html(markdown.as_str(), &options))
         };
-        let html = Some(markdown_to_html(
-            c.markdown.as_ref().unwrap().as_str(),
-            &options,
-        ));
-        c.html = html;
+    }
+
+    // sanitized before embedding the assets, as the data URIs are not allowed
+    if let (true, Some(html)) = (opts.safe, c.html.as_ref()) {
+        c.html = Some(sanitize_html(html));
+    }
+
+    if let (Some(max_size), Some(html)) = (opts.embed_max_size, c.html.as_ref()) {
+        c.html = Some(embed_html_assets(html, &base, root, max_size));
     }
 
     Ok(())
 }
 
 /// Generate content representations from directory contents.
-pub fn init_dir_sections(root: &Path) -> Result<std::vec::Vec<Content>, Box<dyn Error>> {
-    let paths = fs::read_dir(root).map_err(|err| {
-        format!(
-            "Error opening file: {}. {}",
-            root.display(),
-            err.to_string()
-        )
-    })?;
-
-    let mut dirs = paths
+/// Subdirectories are discovered recursively up to `max_depth` levels deep.
+pub fn init_dir_sections(root: &Path, opts: &DiscoverOptions) -> Result<std::vec::Vec<Content>> {
+    dir_sections(root, root, 1, opts, &DirConfig::default())
+}
+
+/// Generate content representations for a `dir` entry of the contents.
+/// The files of the directory are grouped under a heading, followed by a separate group for each of its subdirectories.
+pub fn init_dir_entry_contents(
+    root: &Path,
+    path: &Path,
+    opts: &DiscoverOptions,
+) -> Result<std::vec::Vec<Content>> {
+    let config = DirConfig::read(path)?;
+    let opts = dir_options(&config, opts);
+
+    let base_opts = DiscoverOptions {
+        max_depth: Some(1),
+        ..opts.clone()
+    };
+
+    let mut res = Vec::new();
+    if let Some(mut contents) = dir_contents(root, path, 1, &base_opts, &config)? {
+        res.append(&mut contents);
+    }
+
+    if config.contents.is_none() {
+        res.append(&mut dir_sections(path, path, 1, &opts, &config)?);
+    }
+
+    Ok(res)
+}
+
+/// Generate content representations from the subdirectories of the path at the given depth,
+/// leaving out the ones that are ignored or excluded in the config of the path.
+fn dir_sections(
+    root: &Path,
+    path: &Path,
+    depth: usize,
+    opts: &DiscoverOptions,
+    config: &DirConfig,
+) -> Result<std::vec::Vec<Content>> {
+    if opts.max_depth.map(|max| depth > max).unwrap_or(false) {
+        return Ok(Vec::new());
+    }
+
+    let paths = read_dir(path)?;
+
+    let exclude = config.filter(path)?;
+
+    let dirs = paths
         .filter_map(|p| {
             if let Ok(entry) = p {
                 if let Ok(file_type) = entry.file_type() {
@@ -249,57 +448,125 @@ pub fn init_dir_sections(root: &Path) -> Result<std::vec::Vec<Content>, Box<dyn
 
             None
         })
+        .filter(|dir| !opts.filter.is_ignored(dir, true) && !exclude.is_ignored(dir, true))
         .collect::<Vec<PathBuf>>();
 
-    dirs.sort();
+    let mut groups = Vec::new();
+    for dir in dirs {
+        if let Some(contents) = init_dir_contents(root, &dir, depth, opts)? {
+            groups.push((dir, contents));
+        }
+    }
 
-    let sections = dirs
-        .into_iter()
-        .filter_map(|path| init_dir_contents(root, &path))
-        .flatten()
-        .collect::<Vec<Content>>();
+    order_sections(&mut groups, path, opts.sort)?;
 
-    Ok(sections)
+    Ok(groups
+        .into_iter()
+        .flat_map(|(_, contents)| contents)
+        .collect())
 }
 
 /// Initialize content representations from directory contents.
-pub fn init_dir_contents(root: &Path, path: &Path) -> Option<std::vec::Vec<Content>> {
-    let entries = fs::read_dir(path).expect("could not read dir");
-
-    let mut dirres = entries
-        .filter_map(|de| {
-            if let Ok(dentry) = de {
-                if let Ok(de_file_type) = dentry.file_type() {
-                    if de_file_type.is_file() {
-                        return init_entry_contents(root, dentry, false).map(|(c, _)| vec![c]);
-                    }
+/// The contents of the directory are grouped under a heading, followed by the groups of the nested
+/// subdirectories up to `max_depth`. The nested groups have their `level` set to the depth.
+/// The config in the directory, if any, is applied to the group.
+pub fn init_dir_contents(
+    root: &Path,
+    path: &Path,
+    depth: usize,
+    opts: &DiscoverOptions,
+) -> Result<Option<std::vec::Vec<Content>>> {
+    let config = DirConfig::read(path)?;
+    let opts = dir_options(&config, opts);
+
+    dir_contents(root, path, depth, &opts, &config)
+}
+
+/// The discover options for the directory and its subdirectories, with the ones set in its config.
+fn dir_options(config: &DirConfig, opts: &DiscoverOptions) -> DiscoverOptions {
+    DiscoverOptions {
+        sort: config.sort.unwrap_or(opts.sort),
+        split: config.split.or(opts.split),
+        ..opts.clone()
+    }
+}
+
+fn dir_contents(
+    root: &Path,
+    path: &Path,
+    depth: usize,
+    opts: &DiscoverOptions,
+    config: &DirConfig,
+) -> Result<Option<std::vec::Vec<Content>>> {
+    let mut dirres = Vec::new();
+    let mut nested = Vec::new();
+
+    if let Some(contents) = config.contents.clone() {
+        for mut c in contents {
+            // the files are relative to the directory, and the labels set in the config take precedence
+            if let Some(file) = c.file.take() {
+                let label = c.label.take();
+                c.file = Some(path.join(file));
+                c.init_from_file(root)?;
+                if label.is_some() {
+                    c.label = label;
                 }
             }
-            None
-        })
-        .flatten()
-        .collect::<Vec<Content>>();
 
-    if !dirres.is_empty() {
-        let title = build_title_for_dir(
-            path,
-            fs::read_dir(&path).expect("could not read dir"),
-            false,
-        )
-        .unwrap_or_else(|_| panic!("could not get title from path: {}", path.display()));
-        let heading = Content::new_heading(title);
+            dirres.push(c);
+        }
+    } else {
+        let exclude = config.filter(path)?;
+        for entry in read_dir(path)?.flatten() {
+            if opts.filter.is_ignored_entry(&entry) || exclude.is_ignored_entry(&entry) {
+                continue;
+            }
 
-        dirres.sort_by(|a, b| a.file.cmp(&b.file));
+            if let Some((c, _)) = init_entry_contents(root, entry, false)? {
+                dirres.push(c);
+            }
+        }
 
-        dirres.insert(0, heading);
+        order_contents(&mut dirres, path, opts.sort)?;
 
-        let end = Content::new_break();
+        nested = dir_sections(root, path, depth + 1, opts, config)?;
+    }
+
+    if dirres.is_empty() && nested.is_empty() {
+        return Ok(None);
+    }
+
+    let level = if depth > 1 { Some(depth) } else { None };
+    for c in dirres.iter_mut() {
+        c.level = level;
+        if c.split.is_none() {
+            c.split = opts.split;
+        }
+    }
+
+    let title = match config.label.clone() {
+        Some(label) => label,
+        None => {
+            let title = build_title_for_dir(path, read_dir(path)?, false)?;
+            if opts.sort == SortOrder::Numeric {
+                String::from(strip_numeric_prefix(&title))
+            } else {
+                title
+            }
+        }
+    };
+    let mut heading = Content::new_heading(title);
+    heading.level = level;
 
-        dirres.push(end);
+    dirres.insert(0, heading);
+    dirres.append(&mut nested);
 
-        return Some(dirres);
+    // only the top level groups are separated
+    if depth == 1 {
+        dirres.push(Content::new_break());
     }
-    None
+
+    Ok(Some(dirres))
 }
 
 /// Initialize directory entry content.
@@ -308,15 +575,23 @@ pub fn init_entry_contents(
     root: &Path,
     entry: std::fs::DirEntry,
     check_type: bool,
-) -> Option<(Content, ContentType)> {
+) -> Result<Option<(Content, ContentType)>> {
     if let Ok(file_type) = entry.file_type() {
         if file_type.is_file() {
             let entry_path = entry.path();
 
             if is_ext(&entry_path, "md") {
-                let mut c = Content::default();
-                c.file = Some(entry_path);
-                c.init_from_file(root);
+                let mut c = Content {
+                    file: Some(entry_path.clone()),
+                    ..Content::default()
+                };
+                c.init_from_file(root)?;
+
+                if c.draft == Some(true) {
+                    info!("skipping draft: {}", entry_path.display());
+                    return Ok(None);
+                }
+
                 let mut ct = ContentType::Normal;
 
                 let is_index = check_type && is_index_file(&entry);
@@ -344,12 +619,12 @@ pub fn init_entry_contents(
                     ct = ContentType::Header;
                 }
 
-                return Some((c, ct));
+                return Ok(Some((c, ct)));
             }
         }
     }
 
-    None
+    Ok(None)
 }
 
 #[cfg(test)]
@@ -361,21 +636,75 @@ mod tests {
         // empty
         let mut c = Content::new(None);
         let root = Path::new(".");
-        assert!(fill_content(&mut c, &root).is_ok());
+        assert!(fill_content(&mut c, root, &RenderOptions::default()).is_ok());
         assert_eq!(c, Content::new(None));
 
         // just markdown
         c = Content::new(None);
         c.markdown = Some(String::from("# Hello world!"));
-        assert!(fill_content(&mut c, &root).is_ok());
+        assert!(fill_content(&mut c, root, &RenderOptions::default()).is_ok());
         let mut expected = Content::new(None);
         expected.markdown = Some(String::from("# Hello world!"));
         expected.html = Some(String::from("<h1>Hello world!</h1>\n"));
         assert_eq!(c, expected);
 
+        // with table of contents
+        c = Content::new(None);
+        c.label = Some(String::from("API"));
+        c.markdown = Some(String::from("# API\n\n## Usage"));
+        let opts = RenderOptions {
+            toc: true,
+            ..RenderOptions::default()
+        };
+        assert!(fill_content(&mut c, root, &opts).is_ok());
+        assert_eq!(
+            c.toc,
+            Some(vec![TocEntry {
+                level: 2,
+                title: String::from("Usage"),
+                id: String::from("api-usage"),
+            }])
+        );
+        assert!(c
+            .html
+            .unwrap()
+            .contains(r##"<h2><a href="#usage" aria-hidden="true" class="anchor" id="api-usage"></a>Usage</h2>"##));
+
+        // with highlighting
+        c = Content::new(None);
+        c.markdown = Some(String::from(
+            "```json\n{ \"toc\": true }\n```\n\n```\nplain\n```",
+        ));
+        let opts = RenderOptions {
+            highlight: true,
+            ..RenderOptions::default()
+        };
+        assert!(fill_content(&mut c, root, &opts).is_ok());
+        let html = c.html.unwrap();
+        assert!(html.starts_with(r#"<pre class="hl-code"><code class="language-json"><span"#));
+        assert!(html.ends_with("<pre><code>plain\n</code></pre>\n"));
+
+        // with highlighting and without raw HTML
+        c = Content::new(None);
+        c.markdown = Some(String::from("<b>raw</b>\n\n```json\n{}\n```"));
+        let opts = RenderOptions {
+            highlight: true,
+            markdown: MarkdownOptions {
+                unsafe_html: Some(false),
+                ..MarkdownOptions::default()
+            },
+            ..RenderOptions::default()
+        };
+        assert!(fill_content(&mut c, root, &opts).is_ok());
+        let html = c.html.unwrap();
+        assert!(html.starts_with(
+            "<p><!-- raw HTML omitted -->raw<!-- raw HTML omitted --></p>\n<pre class=\"hl-code\">"
+        ));
+        assert!(html.contains(r#"<code class="language-json">"#));
+
         // with file
         c = Content::new(Some(PathBuf::from("tests/fixtures/utils3/README.md")));
-        assert!(fill_content(&mut c, &root).is_ok());
+        assert!(fill_content(&mut c, root, &RenderOptions::default()).is_ok());
         let mut expected = Content::new(Some(PathBuf::from("tests/fixtures/utils3/README.md")));
         expected.label = Some(String::from("Main page"));
         expected.markdown = Some(String::from("# Main page\n\nSome content."));
@@ -386,12 +715,12 @@ mod tests {
         c = Content::new(Some(PathBuf::from(
             "tests/fixtures/utils3/readme_unknown.md",
         )));
-        assert!(fill_content(&mut c, &root).is_err());
+        assert!(fill_content(&mut c, root, &RenderOptions::default()).is_err());
 
         // with url
         c = Content::new(Some(PathBuf::from("tests/fixtures/utils3/README.md")));
         c.url = Some(String::from("https://github.com"));
-        assert!(fill_content(&mut c, &root).is_ok());
+        assert!(fill_content(&mut c, root, &RenderOptions::default()).is_ok());
         let mut expected = Content::new(Some(PathBuf::from("tests/fixtures/utils3/README.md")));
         expected.markdown = None;
         expected.html = None;
@@ -401,10 +730,116 @@ mod tests {
         // with html
         c = Content::new(Some(PathBuf::from("tests/fixtures/utils3/README.md")));
         c.html = Some(String::from("<h1>Some title</h1>"));
-        assert!(fill_content(&mut c, &root).is_ok());
+        assert!(fill_content(&mut c, root, &RenderOptions::default()).is_ok());
         let mut expected = Content::new(Some(PathBuf::from("tests/fixtures/utils3/README.md")));
         expected.markdown = None;
         expected.html = Some(String::from("<h1>Some title</h1>"));
         assert_eq!(c, expected);
     }
+
+    #[test]
+    fn test_init_dir_sections() {
+        let root = Path::new("tests/fixtures/nested");
+        let menu = |contents: Vec<Content>| {
+            contents
+                .into_iter()
+                .map(|c| (c.is_heading, c.label, c.level))
+                .collect::<Vec<(Option<bool>, Option<String>, Option<usize>)>>()
+        };
+        let heading = |label: &str, level| (Some(true), Some(String::from(label)), level);
+        let item = |label: &str, level| (None, Some(String::from(label)), level);
+        let end = (Some(false), None, None);
+
+        assert_eq!(
+            menu(init_dir_sections(root, &DiscoverOptions::default()).unwrap()),
+            vec![
+                heading("guide", None),
+                item("Introduction", None),
+                heading("advanced", Some(2)),
+                item("Tuning", Some(2)),
+                heading("internals", Some(3)),
+                item("Cache", Some(3)),
+                end.clone(),
+                heading("reference", None),
+                heading("api", Some(2)),
+                item("Client", Some(2)),
+                end.clone(),
+            ]
+        );
+
+        assert_eq!(
+            menu(
+                init_dir_sections(
+                    root,
+                    &DiscoverOptions {
+                        max_depth: Some(1),
+                        ..DiscoverOptions::default()
+                    }
+                )
+                .unwrap()
+            ),
+            vec![
+                heading("guide", None),
+                item("Introduction", None),
+                end.clone(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dir_config() {
+        let root = Path::new("tests/fixtures/dir_config");
+        let menu = |contents: Vec<Content>| {
+            contents
+                .into_iter()
+                .map(|c| (c.is_heading, c.label, c.level))
+                .collect::<Vec<(Option<bool>, Option<String>, Option<usize>)>>()
+        };
+        let heading = |label: &str, level| (Some(true), Some(String::from(label)), level);
+        let item = |label: &str, level| (None, Some(String::from(label)), level);
+        let end = (Some(false), None, None);
+
+        assert_eq!(
+            menu(init_dir_sections(root, &DiscoverOptions::default()).unwrap()),
+            vec![
+                heading("API Reference", None),
+                item("Server", None),
+                item("The Client", None),
+                end.clone(),
+                heading("User Guide", None),
+                item("Second", None),
+                item("Tenth", None),
+                heading("tips", Some(2)),
+                item("Tip", Some(2)),
+                end.clone(),
+            ]
+        );
+
+        let guide = root.join("guide");
+        assert_eq!(
+            menu(init_dir_entry_contents(root, &guide, &DiscoverOptions::default()).unwrap()),
+            vec![
+                heading("User Guide", None),
+                item("Second", None),
+                item("Tenth", None),
+                end.clone(),
+                heading("tips", None),
+                item("Tip", None),
+                end.clone(),
+            ]
+        );
+
+        let api = root.join("api");
+        let contents = init_dir_entry_contents(root, &api, &DiscoverOptions::default()).unwrap();
+        assert_eq!(contents[2].file, Some(api.join("client.md")));
+        assert_eq!(
+            menu(contents),
+            vec![
+                heading("API Reference", None),
+                item("Server", None),
+                item("The Client", None),
+                end.clone(),
+            ]
+        );
+    }
 }
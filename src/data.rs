@@ -1,23 +1,35 @@
 //! Data serves both as the configuration data for mdPage
 //! as well as the actual template data for generating content.
 
+use std::collections::HashMap;
 use std::env;
-use std::error::Error;
 
 use std::fs;
-use std::fs::File;
 
-use std::io::prelude::*;
-use std::io::BufReader;
 use std::path::Path;
 use std::path::PathBuf;
+use std::str::FromStr;
 
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
+use crate::assets::{embed_asset, DEFAULT_EMBED_MAX_SIZE};
+use crate::config::{merge_layers, resolve_config};
 use crate::content::{
-    init_dir_contents, init_dir_sections, init_entry_contents, Content, ContentType,
+    header_id_prefix, init_dir_entry_contents, init_dir_sections, init_entry_contents, Content,
+    ContentType, DiscoverOptions, RenderOptions, DEFAULT_MAX_DEPTH,
 };
-use crate::utils::{build_title_for_dir, is_ext};
+use crate::error::{Error, Result};
+use crate::filter::PathFilter;
+use crate::links::{rewrite_html_links, LinkTarget};
+use crate::markdown::MarkdownOptions;
+use crate::order::{order_contents, SortOrder};
+use crate::split::{split_content, split_contents, SplitLevel};
+use crate::summary::{read_summary, summary_file, Book};
+use crate::toc::heading_ids;
+use crate::utils::{build_title_for_dir, is_ext, read_dir, resolve_path};
+use crate::writer::page_files;
 
 /// Data serves both as the configuration data for mdPage
 /// as well as the actual template data for generating content.
@@ -25,6 +37,39 @@ use crate::utils::{build_title_for_dir, is_ext};
 pub struct Data {
     /// Whether to do full page or not.
     pub full_page: Option<bool>,
+    /// Whether to inline all the styles and scripts so the document has no external dependencies.
+    pub self_contained: Option<bool>,
+    /// Whether to embed local images and other assets as data URIs.
+    pub embed_assets: Option<bool>,
+    /// The maximum size of an asset to embed in bytes, which defaults to 1 MiB.
+    pub embed_max_size: Option<u64>,
+    /// Whether to add a search box with a full text search index of all the contents.
+    pub search: Option<bool>,
+    /// Whether to add a table of contents of the headings within each of the contents to the menu.
+    pub toc: Option<bool>,
+    /// Whether to highlight the syntax of the fenced code blocks.
+    pub highlight: Option<bool>,
+    /// The theme used to highlight the code blocks, which defaults to "InspiredGitHub".
+    pub highlight_theme: Option<String>,
+    /// The markdown extensions and rendering options.
+    pub markdown: Option<MarkdownOptions>,
+    /// Whether to sanitize all the rendered HTML and reject the inline `script`,
+    /// for documentation written by untrusted contributors.
+    pub safe: Option<bool>,
+    /// The maximum depth of the subdirectories discovered for the contents, which is 1 by default,
+    /// so only the immediate subdirectories of the root are discovered.
+    pub max_depth: Option<usize>,
+    /// The strategy used to order the discovered contents, which is by file name by default.
+    pub sort: Option<SortOrder>,
+    /// The heading level to split the markdown contents at, `h1` or `h2`, unless set for the content
+    /// or its directory. The contents are not split by default.
+    pub split: Option<SplitLevel>,
+    /// The glob patterns of the files to discover, relative to the root. All files are discovered if not set.
+    pub include: Option<Vec<String>>,
+    /// The glob patterns of the files and directories to leave out of the discovered contents.
+    pub exclude: Option<Vec<String>>,
+    /// Whether to generate a separate page for each of the contents instead of a single document.
+    pub multi_page: Option<bool>,
     /// Title used in header and title of the document.
     pub title: Option<String>,
     /// Subtitle used in header.
@@ -47,6 +92,17 @@ pub struct Data {
     pub header: Option<Content>,
     /// Custom footer content.
     pub footer: Option<Content>,
+    /// The config file to read instead of looking for one in the root directory.
+    #[serde(skip)]
+    pub config: Option<PathBuf>,
+    /// Whether to look for the config file in the ancestor directories of the root
+    /// when there is none in the root directory.
+    #[serde(skip)]
+    pub search_parents: Option<bool>,
+    /// The directory with custom Handlebars templates.
+    /// Any of `index.hbs`, `multi.hbs`, `full.hbs` and `css.hbs` replace the built-in templates,
+    /// and all other `.hbs` files are registered as additional partials.
+    pub template_dir: Option<PathBuf>,
 }
 
 /// Link represents a link we can insert into the head of the generated document.
@@ -66,20 +122,28 @@ pub struct Link {
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum LinkType {
+    #[serde(alias = "stylesheet")]
     Style,
     Script,
 }
 
-impl LinkType {
-    pub fn from_str(s: &str) -> Option<LinkType> {
+impl FromStr for LinkType {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<LinkType, String> {
         match s {
-            "style" => Some(LinkType::Style),
-            "stylesheet" => Some(LinkType::Style),
-            "script" => Some(LinkType::Script),
-            _ => None,
+            "style" => Ok(LinkType::Style),
+            "stylesheet" => Ok(LinkType::Style),
+            "script" => Ok(LinkType::Script),
+            _ => Err(format!(
+                "unknown link type: {}. Expected style or script",
+                s
+            )),
         }
     }
+}
 
+impl LinkType {
     pub fn as_str(&self) -> &'static str {
         match self {
             LinkType::Style => "stylesheet",
@@ -88,10 +152,75 @@ impl LinkType {
     }
 }
 
+/// The configuration of a subdirectory, read from the `mdpage.dir.json`, `mdpage.dir.toml`, `mdpage.dir.yaml` or `mdpage.dir.yml` file within it.
+/// It applies only to the group of contents discovered for the directory, which is merged into the parent contents.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct DirConfig {
+    /// The label of the heading of the group, which defaults to the directory name.
+    pub label: Option<String>,
+    /// The strategy used to order the contents of the directory and its subdirectories.
+    pub sort: Option<SortOrder>,
+    /// The glob patterns of the files and subdirectories to leave out, relative to the directory.
+    pub exclude: Option<Vec<String>>,
+    /// The heading level to split the markdown contents of the directory and its subdirectories at.
+    pub split: Option<SplitLevel>,
+    /// The contents of the group, used instead of discovering them.
+    /// The files are relative to the directory.
+    pub contents: Option<Vec<Content>>,
+}
+
+impl DirConfig {
+    /// Read the config in the directory, or the default config if there is none.
+    /// The properties that only apply to the root config are ignored with a warning.
+    pub(crate) fn read(dir: &Path) -> Result<DirConfig> {
+        let path = match find_config_file(dir, &DIR_CONFIG_FILES) {
+            Some(path) => path,
+            None => return Ok(DirConfig::default()),
+        };
+
+        let props: Map<String, Value> = read_config(&path)?;
+        for name in props.keys() {
+            if !DIR_CONFIG_PROPERTIES.contains(&name.as_str()) {
+                warn!(
+                    "unsupported property in directory config: {}. Ignoring {}",
+                    path.display(),
+                    name
+                );
+            }
+        }
+
+        serde_json::from_value(Value::Object(props)).map_err(|err| Error::Config {
+            path,
+            source: Box::new(err),
+        })
+    }
+
+    /// The filter of the files and subdirectories excluded in the directory.
+    pub(crate) fn filter(&self, dir: &Path) -> Result<PathFilter> {
+        PathFilter::new(dir, &[], self.exclude.as_deref().unwrap_or_default())
+    }
+}
+
 impl Default for Data {
     fn default() -> Data {
         Data {
             full_page: Some(false),
+            self_contained: None,
+            embed_assets: None,
+            embed_max_size: None,
+            search: None,
+            toc: None,
+            highlight: None,
+            highlight_theme: None,
+            markdown: None,
+            safe: None,
+            max_depth: None,
+            sort: None,
+            split: None,
+            include: None,
+            exclude: None,
+            multi_page: None,
             title: None,
             subtitle: None,
             author: None,
@@ -101,89 +230,141 @@ impl Default for Data {
             script: None,
             style: None,
             links: None,
-            footer: None,
             header: None,
+            footer: None,
+            config: None,
+            search_parents: None,
+            template_dir: None,
         }
     }
 }
 
 impl Data {
-    fn build(&mut self, root: &Path) -> Result<(), Box<dyn Error>> {
+    /// The data with none of the properties set, used to merge the layers of the configuration.
+    pub(crate) fn empty() -> Data {
+        Data {
+            full_page: None,
+            ..Data::default()
+        }
+    }
+
+    pub(crate) fn build(&mut self, root: &Path) -> Result<()> {
+        self.check_safe()?;
+
         self.init(root)?;
 
         self.build_contents(root)?;
 
+        self.rewrite_links(root);
+
         Ok(())
     }
 
-    fn init(&mut self, root: &Path) -> Result<(), Box<dyn Error>> {
+    /// Build the data from the given files instead of discovering the contents in the root directory.
+    /// The first file is the main content, unless there is only one, which is split at its headings.
+    pub(crate) fn build_files(&mut self, root: &Path, files: Vec<Content>) -> Result<()> {
+        self.check_safe()?;
+
+        self.init_files(root)?;
+
+        let mut files = files;
+        for c in files.iter_mut() {
+            c.init_from_file(root)?;
+            c.init_from_markdown()?;
+        }
+
         if self.title.is_none() {
-            self.title = Some(build_title_for_dir(
-                root,
-                fs::read_dir(root).map_err(|err| {
-                    format!("Error reading dir: {}. {}", root.display(), err.to_string())
-                })?,
-                true,
-            )?);
+            self.title = files.first().and_then(|c| c.label.clone());
         }
 
-        if self.main.is_some() {
-            self.main.as_mut().unwrap().init_from_file(root);
+        let (main, mut contents) = match files.len() {
+            0 => (None, files),
+            1 => split_content(files.remove(0), root, self.split.map(SplitLevel::level))?,
+            _ => (Some(files.remove(0)), files),
+        };
+
+        match main {
+            Some(main) if self.main.is_some() => contents.insert(0, main),
+            Some(main) => self.main = Some(main),
+            None => {}
         }
+        self.contents = Some(contents);
+
+        self.build_contents(root)?;
+
+        self.rewrite_links(root);
+
+        Ok(())
+    }
 
-        if self.header.is_some() {
-            self.header.as_mut().unwrap().init_from_file(root);
+    fn check_safe(&self) -> Result<()> {
+        if self.safe == Some(true) && self.script.is_some() {
+            return Err(Error::Unsafe {
+                property: String::from("script"),
+            });
         }
 
-        if self.footer.is_some() {
-            self.footer.as_mut().unwrap().init_from_file(root);
+        Ok(())
+    }
+
+    fn init(&mut self, root: &Path) -> Result<()> {
+        // the metadata of an mdBook project is used when not set in the config
+        let book = Book::read(root)?;
+        if let Some(meta) = book.as_ref().map(|b| &b.book) {
+            if self.title.is_none() {
+                self.title = meta.title.clone();
+            }
+            if self.author.is_none() {
+                self.author = meta.authors.as_ref().map(|authors| authors.join(", "));
+            }
         }
 
+        if self.title.is_none() {
+            self.title = Some(build_title_for_dir(root, read_dir(root)?, true)?);
+        }
+
+        self.init_files(root)?;
+
         let mut main = None;
         let mut header = None;
         let mut footer = None;
 
-        let paths = fs::read_dir(root)
-            .map_err(|err| format!("Error reading dir: {}. {}", root.display(), err.to_string()))?;
-
-        let mut res = paths
-            .filter_map(|p| {
-                if let Ok(entry) = p {
-                    if let Ok(file_type) = entry.file_type() {
-                        if file_type.is_file() {
-                            return init_entry_contents(root, entry, true).and_then(|(c, ct)| {
-                                match ct {
-                                    ContentType::Main => {
-                                        main = Some(c);
-                                        None
-                                    }
-                                    ContentType::Footer => {
-                                        footer = Some(c);
-                                        None
-                                    }
-                                    ContentType::Header => {
-                                        header = Some(c);
-                                        None
-                                    }
-                                    _ => Some(vec![c]),
-                                }
-                            });
-                        }
-                    }
-                }
-                None
-            })
-            .flatten()
-            .collect::<Vec<Content>>();
+        let opts = self.discover_options(root)?;
+
+        let mut res = Vec::new();
+        for entry in read_dir(root)?.flatten() {
+            if opts.filter.is_ignored_entry(&entry) {
+                continue;
+            }
+
+            match init_entry_contents(root, entry, true)? {
+                Some((c, ContentType::Main)) => main = Some(c),
+                Some((c, ContentType::Footer)) => footer = Some(c),
+                Some((c, ContentType::Header)) => header = Some(c),
+                Some((c, ContentType::Normal)) => res.push(c),
+                None => {}
+            }
+        }
 
-        res.sort_by(|a, b| a.file.cmp(&b.file));
+        // the summary lists the contents instead of discovering them, usually starting with the introduction
+        let summary = match summary_file(root, book.as_ref()) {
+            Some(path) if self.contents.is_none() => Some(read_summary(&path)?),
+            _ => None,
+        };
+
+        if let Some(contents) = summary {
+            main = None;
+            res = contents;
+        } else {
+            order_contents(&mut res, root, opts.sort)?;
 
-        let mut sections = init_dir_sections(root)?;
+            let mut sections = init_dir_sections(root, &opts)?;
 
-        if !res.is_empty() {
-            res.push(Content::new_break());
+            if !res.is_empty() {
+                res.push(Content::new_break());
+            }
+            res.append(&mut sections);
         }
-        res.append(&mut sections);
 
         if self.main.is_none() {
             self.main = main;
@@ -204,7 +385,177 @@ impl Data {
         Ok(())
     }
 
-    fn build_contents(&mut self, root: &Path) -> Result<(), Box<dyn Error>> {
+    /// Initialize the template directory and the labels of the main, header and footer files.
+    fn init_files(&mut self, root: &Path) -> Result<()> {
+        if let Some(dir) = self.template_dir.as_ref() {
+            self.template_dir = Some(root.join(dir));
+        }
+
+        if let Some(main) = self.main.as_mut() {
+            main.init_from_file(root)?;
+        }
+
+        if let Some(header) = self.header.as_mut() {
+            header.init_from_file(root)?;
+        }
+
+        if let Some(footer) = self.footer.as_mut() {
+            footer.init_from_file(root)?;
+        }
+
+        Ok(())
+    }
+
+    /// The options for discovering the contents in the directories.
+    fn discover_options(&self, root: &Path) -> Result<DiscoverOptions> {
+        let filter = PathFilter::new(
+            root,
+            self.include.as_deref().unwrap_or_default(),
+            self.exclude.as_deref().unwrap_or_default(),
+        )?
+        .skip_hidden()
+        .with_ignore_file()?;
+
+        Ok(DiscoverOptions {
+            max_depth: Some(self.max_depth.unwrap_or(DEFAULT_MAX_DEPTH)),
+            sort: self.sort.unwrap_or_default(),
+            filter,
+            split: self.split,
+        })
+    }
+
+    /// The options for rendering the contents.
+    fn render_options(&self) -> RenderOptions {
+        let embed_max_size = if self.embed_assets == Some(true) {
+            Some(self.embed_max_size.unwrap_or(DEFAULT_EMBED_MAX_SIZE))
+        } else {
+            None
+        };
+
+        RenderOptions {
+            embed_max_size,
+            toc: self.toc == Some(true),
+            markdown: self.markdown.clone().unwrap_or_default(),
+            safe: self.safe == Some(true),
+            highlight: self.highlight == Some(true),
+        }
+    }
+
+    /// Rewrite the links between the content files into links to the contents within the document.
+    fn rewrite_links(&mut self, root: &Path) {
+        let targets = self.link_targets(root);
+        if targets.is_empty() {
+            return;
+        }
+
+        let all = self
+            .main
+            .iter_mut()
+            .chain(self.header.iter_mut())
+            .chain(self.footer.iter_mut())
+            .chain(self.contents.iter_mut().flatten());
+
+        for c in all {
+            if let Some(html) = c.html.as_ref() {
+                // relative links are resolved against the directory of the content file
+                let base = c
+                    .file
+                    .as_ref()
+                    .and_then(|file| canonical_file(root, file))
+                    .and_then(|path| path.parent().map(Path::to_path_buf))
+                    .unwrap_or_else(|| root.to_path_buf());
+
+                c.html = Some(rewrite_html_links(html, &base, &targets));
+            }
+        }
+    }
+
+    /// Determine the location of each content file within the generated document,
+    /// keyed by the canonical file path. A split file has the location of each of its contents.
+    fn link_targets(&self, root: &Path) -> HashMap<PathBuf, Vec<LinkTarget>> {
+        let multi_page = self.multi_page == Some(true);
+        let full_page = self.full_page == Some(true) && !multi_page;
+
+        let empty = Vec::new();
+        let contents = self.contents.as_ref().unwrap_or(&empty);
+        let main = self.main.as_ref().filter(|m| m.html.is_some());
+        let header_ids = self.render_options().header_ids();
+        let header_prefix = |c: &Content| {
+            if header_ids {
+                Some(header_id_prefix(c.label.as_deref()))
+            } else {
+                None
+            }
+        };
+        let options = self.render_options().markdown.comrak_options();
+        let headings = |c: &Content| {
+            c.markdown
+                .as_ref()
+                .map(|markdown| heading_ids(markdown, &options))
+                .unwrap_or_default()
+        };
+
+        let files = if multi_page {
+            page_files(contents, main.is_some())
+        } else {
+            vec![None; contents.len()]
+        };
+
+        let mut targets = HashMap::new();
+
+        if let Some(main) = main {
+            if let Some(path) = main.file.as_ref().and_then(|f| canonical_file(root, f)) {
+                let page = if multi_page { "index.html" } else { "" };
+                targets.insert(
+                    path,
+                    vec![LinkTarget {
+                        page: String::from(page),
+                        anchor: String::from("doc-section-main"),
+                        header_prefix: header_prefix(main),
+                        headings: headings(main),
+                    }],
+                );
+            }
+        }
+
+        for (i, (c, file)) in contents.iter().zip(files).enumerate() {
+            if c.html.is_none()
+                || c.is_heading == Some(true)
+                || c.is_break == Some(true)
+                || c.url.is_some()
+            {
+                continue;
+            }
+
+            let path = match c.file.as_ref().and_then(|f| canonical_file(root, f)) {
+                Some(path) => path,
+                None => continue,
+            };
+
+            // the full page template uses the label as the ID of the content
+            let anchor = match c.label.as_ref() {
+                Some(label) if full_page && !label.is_empty() => label.clone(),
+                _ => format!("doc-section-{}", i),
+            };
+
+            targets
+                .entry(path)
+                .or_insert_with(Vec::new)
+                .push(LinkTarget {
+                    page: file.unwrap_or_default(),
+                    anchor,
+                    header_prefix: header_prefix(c),
+                    headings: headings(c),
+                });
+        }
+
+        targets
+    }
+
+    fn build_contents(&mut self, root: &Path) -> Result<()> {
+        let opts = self.render_options();
+        let discover_opts = self.discover_options(root)?;
+
         if self.contents.is_some() {
             let mut contents = self.contents.as_mut().unwrap();
 
@@ -214,30 +565,12 @@ impl Data {
                 let mut index = 0;
                 while index < contents.len() {
                     // fix dir entries
-                    if contents[index].dir.is_some() {
-                        let mut pathbuf = contents[index].dir.clone().unwrap();
-
-                        if root.has_root() && pathbuf.is_relative() {
-                            pathbuf = root.join(&pathbuf).canonicalize().unwrap_or_else(|_| {
-                                panic!(
-                                    "could not resolve path. root: {} path: {}",
-                                    root.display(),
-                                    pathbuf.display()
-                                )
-                            });
-                        }
+                    if let Some(dir) = contents[index].dir.as_ref() {
+                        let pathbuf = resolve_path(root, dir)?;
 
                         if pathbuf.is_dir() {
-                            let mut dir_contents = Vec::new();
-
-                            // get the base files
-                            if let Some(mut root_dir_contents) = init_dir_contents(root, &pathbuf) {
-                                dir_contents.append(&mut root_dir_contents);
-                            }
-
-                            // do subdirs
-                            let mut sub_dir_contents = init_dir_sections(&pathbuf)?;
-                            dir_contents.append(&mut sub_dir_contents);
+                            let dir_contents =
+                                init_dir_entry_contents(root, &pathbuf, &discover_opts)?;
 
                             // add into the overall
                             let mut di = 0;
@@ -256,117 +589,193 @@ impl Data {
                 self.contents = Some(expanded_contents);
             }
 
+            let split = self.contents.take().unwrap_or_default();
+            self.contents = Some(split_contents(split, root, self.split)?);
+
             contents = self.contents.as_mut().unwrap();
 
-            for c in contents {
-                crate::content::fill_content(c, root)?;
+            for c in contents.iter_mut() {
+                crate::content::fill_content(c, root, &opts)?;
             }
+
+            contents.retain(|c| c.draft != Some(true));
         }
 
-        if self.main.is_some() {
-            crate::content::fill_content(self.main.as_mut().unwrap(), root)?;
+        if let Some(main) = self.main.as_mut() {
+            crate::content::fill_content(main, root, &opts)?;
         }
 
-        if self.header.is_some() {
-            crate::content::fill_content(self.header.as_mut().unwrap(), root)?;
+        if let Some(header) = self.header.as_mut() {
+            crate::content::fill_content(header, root, &opts)?;
         }
 
-        if self.footer.is_some() {
-            crate::content::fill_content(self.footer.as_mut().unwrap(), root)?;
+        if let Some(footer) = self.footer.as_mut() {
+            crate::content::fill_content(footer, root, &opts)?;
+        }
+
+        if let (Some(max_size), Some(icon)) = (opts.embed_max_size, self.icon.as_ref()) {
+            if let Some(data) = embed_asset(icon, root, root, max_size) {
+                self.icon = Some(data);
+            }
         }
 
         Ok(())
     }
 }
 
-fn config_file(root: &Path) -> Option<PathBuf> {
-    let mut r = Path::new(root);
-    let json_config = r.join("mdpage.json");
-    if json_config.as_path().exists() {
-        Some(json_config)
+/// The names of the config files, in the order of precedence.
+const CONFIG_FILES: [&str; 4] = ["mdpage.json", "mdpage.toml", "mdpage.yaml", "mdpage.yml"];
+
+/// The names of the configs of the subdirectories, in the order of precedence.
+/// These are distinct from the root config files, so a subdirectory with its own root config is not mistaken for one.
+const DIR_CONFIG_FILES: [&str; 4] = [
+    "mdpage.dir.json",
+    "mdpage.dir.toml",
+    "mdpage.dir.yaml",
+    "mdpage.dir.yml",
+];
+
+/// The properties of the directory config.
+const DIR_CONFIG_PROPERTIES: [&str; 5] = ["label", "sort", "exclude", "split", "contents"];
+
+/// Find the config file in the root directory, warning if there is more than one.
+pub(crate) fn config_file(root: &Path) -> Option<PathBuf> {
+    find_config_file(root, &CONFIG_FILES)
+}
+
+fn find_config_file(root: &Path, names: &[&str]) -> Option<PathBuf> {
+    let found = names
+        .iter()
+        .map(|name| root.join(name))
+        .filter(|path| path.is_file())
+        .collect::<Vec<PathBuf>>();
+
+    if found.len() > 1 {
+        let names = found
+            .iter()
+            .filter_map(|path| path.file_name())
+            .map(|name| name.to_string_lossy())
+            .collect::<Vec<_>>();
+        warn!(
+            "multiple config files found in {}: {}. Using {}",
+            root.display(),
+            names.join(", "),
+            names[0]
+        );
+    }
+
+    found.into_iter().next()
+}
+
+/// Find the config file for the root directory. This is the config file set in the data if any,
+/// otherwise the one in the root directory, or the closest ancestor directory if `search_parents` is set.
+pub(crate) fn find_config(root: &Path, data: &Data) -> Result<Option<PathBuf>> {
+    if let Some(path) = data.config.as_ref() {
+        fs::metadata(path).map_err(|err| Error::read_file(path, err))?;
+        return Ok(Some(path.clone()));
+    }
+
+    if data.search_parents == Some(true) {
+        Ok(root.ancestors().find_map(config_file))
     } else {
-        r = Path::new(root);
-        let toml_config = r.join("mdpage.toml");
-        if toml_config.as_path().exists() {
-            Some(toml_config)
-        } else {
-            None
-        }
+        Ok(config_file(root))
     }
 }
 
-/// Build the content data from a root directory path and optional initial value.
-pub fn build(root: &Path, initial_value: Option<Data>) -> Result<Data, Box<dyn Error>> {
-    let mut r = root;
-    let current_dir = env::current_dir()?;
-    let abs;
+/// Read and parse the JSON, TOML or YAML config file.
+pub(crate) fn read_config<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    info!("reading config: {}", path.display());
+    let content = fs::read_to_string(path).map_err(|err| Error::read_file(path, err))?;
+
+    let config_error = |source| Error::Config {
+        path: path.to_path_buf(),
+        source,
+    };
+
+    if is_ext(path, "toml") {
+        toml::from_str(&content).map_err(|err| config_error(Box::new(err)))
+    } else if is_ext(path, "yaml") || is_ext(path, "yml") {
+        serde_yaml::from_str(&content).map_err(|err| config_error(Box::new(err)))
+    } else {
+        serde_json::from_str(&content).map_err(|err| config_error(Box::new(err)))
+    }
+}
+
+/// Resolve the root directory against the current directory if it is relative.
+pub(crate) fn resolve_root(root: &Path) -> Result<PathBuf> {
     if root.is_relative() {
-        abs = current_dir
-            .as_path()
-            .join(root)
-            .canonicalize()
-            .map_err(|err| {
-                format!(
-                    "could not join current dir {} with path: {}. {}",
-                    current_dir.display(),
-                    root.display(),
-                    err.to_string()
-                )
-            })?;
-        r = abs.as_path();
-    }
-
-    let path = config_file(r);
-    let mut data = initial_value.unwrap_or_default();
-
-    if let Some(file_path) = path {
-        info!("reading config: {}", file_path.display());
-        let mut file = File::open(file_path.as_path()).map_err(|err| {
-            format!(
-                "Error reading file: {}. {}",
-                file_path.display(),
-                err.to_string()
-            )
+        let current_dir = env::current_dir().map_err(|source| Error::ReadDir {
+            path: PathBuf::from("."),
+            source,
         })?;
-        if is_ext(&file_path, "json") {
-            let reader = BufReader::new(file);
-            data = serde_json::from_reader(reader).map_err(|err| {
-                format!(
-                    "Error reading json: {}. {}",
-                    file_path.display(),
-                    err.to_string()
-                )
-            })?;
-        } else if is_ext(&file_path, "toml") {
-            let mut content = String::new();
-            file.read_to_string(&mut content).map_err(|err| {
-                format!(
-                    "Error reading file: {}. {}",
-                    file_path.display(),
-                    err.to_string()
-                )
-            })?;
-            data = toml::from_str(&content).map_err(|err| {
-                format!(
-                    "Error reading toml: {}. {}",
-                    file_path.display(),
-                    err.to_string()
-                )
-            })?;
-        }
-    }
-
-    match data.build(r) {
-        Ok(()) => Ok(data),
-        Err(e) => Err(e),
+        resolve_path(&current_dir, root)
+    } else {
+        Ok(root.to_path_buf())
     }
 }
 
+/// Resolve the content file against the root into the canonical path, if it exists.
+fn canonical_file(root: &Path, file: &Path) -> Option<PathBuf> {
+    resolve_path(root, file).ok()?.canonicalize().ok()
+}
+
+/// Build the content data from a root directory path and optional initial value.
+/// The initial value takes precedence over the config file, see `resolve_config`.
+pub fn build(root: &Path, initial_value: Option<Data>) -> Result<Data> {
+    let r = resolve_root(root)?;
+    let mut data = resolve_config(&r, initial_value)?.data;
+
+    data.build(&r)?;
+
+    Ok(data)
+}
+
+/// Build the content data from markdown files instead of a directory, with the root being the directory
+/// of the first file, or the current directory if there is none. The contents can also be set with `markdown`
+/// instead of `file`, such as the markdown read from the standard input.
+/// Only the config file set in the initial value is read, see `resolve_config`.
+pub fn build_files(files: Vec<Content>, initial_value: Option<Data>) -> Result<Data> {
+    let current_dir = resolve_root(Path::new("."))?;
+
+    let mut files = files;
+    for c in files.iter_mut() {
+        if let Some(file) = c.file.as_ref() {
+            c.file = Some(resolve_path(&current_dir, file)?);
+        }
+    }
+
+    let root = files
+        .iter()
+        .find_map(|c| c.file.as_deref().and_then(Path::parent))
+        .map(Path::to_path_buf)
+        .unwrap_or(current_dir);
+
+    let initial = initial_value.unwrap_or_default();
+    // the config file in the directory of the files is not used implicitly
+    let file = match initial.config.as_ref() {
+        Some(_) => find_config(&root, &initial)?,
+        None => None,
+    };
+    let config = match file.as_ref() {
+        Some(path) => Some(read_config(path)?),
+        None => None,
+    };
+    let mut data = merge_layers(config, initial, file)?.data;
+
+    data.build_files(&root, files)?;
+
+    Ok(data)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use std::fs::File;
+    use std::io::BufReader;
+
     #[test]
+    #[allow(clippy::field_reassign_with_default)]
     fn test_init() {
         // empty
         let mut root = PathBuf::from("tests");
@@ -420,4 +829,484 @@ mod tests {
         expected = serde_json::from_reader(reader).expect("could not read expected data");
         assert_eq!(data, expected);
     }
+
+    #[test]
+    fn test_rewrite_links() {
+        let root = Path::new("tests/fixtures/links");
+        let html = |data: &Data, i: usize| {
+            data.contents.as_ref().unwrap()[i]
+                .html
+                .clone()
+                .unwrap_or_default()
+        };
+
+        // the index is the main content, followed by a "guide" group and a "reference" group
+        let data = build(root, None).unwrap();
+        let main = data.main.as_ref().unwrap().html.clone().unwrap();
+        assert!(main.contains(r##"<a href="#doc-section-1">installation</a>"##));
+        assert!(html(&data, 1).contains(r##"<a href="#doc-section-4">config</a>"##));
+        // without header IDs the links to headings point to the content
+        assert!(html(&data, 1).contains(r##"<a href="#doc-section-4">links</a>"##));
+
+        let initial = Data {
+            toc: Some(true),
+            ..Data::default()
+        };
+        let data = build(root, Some(initial)).unwrap();
+        assert!(html(&data, 1).contains(r##"<a href="#config-reference-links">links</a>"##));
+        assert!(html(&data, 4).contains(r##"<a href="#installation-step-1">step 1</a>"##));
+        assert!(html(&data, 1).contains(r##"id="installation-step-1""##));
+
+        let initial = Data {
+            full_page: Some(true),
+            toc: Some(true),
+            ..Data::default()
+        };
+        let data = build(root, Some(initial)).unwrap();
+        assert!(html(&data, 1).contains(r##"<a href="#Config%20Reference">config</a>"##));
+        assert!(html(&data, 4).contains(r##"<a href="#installation-step-1">step 1</a>"##));
+
+        let initial = Data {
+            multi_page: Some(true),
+            toc: Some(true),
+            ..Data::default()
+        };
+        let data = build(root, Some(initial)).unwrap();
+        let main = data.main.as_ref().unwrap().html.clone().unwrap();
+        assert!(main.contains(r##"<a href="installation.html">installation</a>"##));
+        assert!(html(&data, 1)
+            .contains(r##"<a href="config-reference.html#config-reference-links">links</a>"##));
+    }
+
+    #[test]
+    fn test_markdown_options() {
+        let from_json: Data =
+            serde_json::from_str(r#"{ "markdown": { "footnotes": true, "unsafe": false } }"#)
+                .unwrap();
+        let from_toml: Data =
+            toml::from_str("[markdown]\nfootnotes = true\nunsafe = false\n").unwrap();
+        assert_eq!(from_json, from_toml);
+
+        let markdown = from_json.render_options().markdown;
+        assert_eq!(markdown.footnotes, Some(true));
+        assert_eq!(markdown.unsafe_html, Some(false));
+        assert_eq!(markdown.table, None);
+
+        let mut c = Content::new(None);
+        c.label = Some(String::from("Notes"));
+        c.markdown = Some(String::from("## Raw <b>HTML</b>"));
+        let opts = RenderOptions {
+            markdown: MarkdownOptions {
+                header_ids: Some(true),
+                unsafe_html: Some(false),
+                ..MarkdownOptions::default()
+            },
+            ..RenderOptions::default()
+        };
+        crate::content::fill_content(&mut c, Path::new("."), &opts).unwrap();
+        assert_eq!(
+            c.html.unwrap(),
+            "<h2><a href=\"#raw-html\" aria-hidden=\"true\" class=\"anchor\" id=\"notes-raw-html\"></a>Raw <!-- raw HTML omitted -->HTML<!-- raw HTML omitted --></h2>\n"
+        );
+        assert_eq!(c.toc, None);
+    }
+
+    #[test]
+    fn test_find_config() {
+        let root = Path::new("tests/fixtures/config_search/docs");
+        let data = build(root, None).unwrap();
+        assert_eq!(data.title, Some(String::from("docs")));
+
+        let data = build(
+            root,
+            Some(Data {
+                search_parents: Some(true),
+                ..Data::default()
+            }),
+        )
+        .unwrap();
+        assert_eq!(data.title, Some(String::from("Shared config")));
+        assert_eq!(
+            data.subtitle,
+            Some(String::from("Found in a parent directory"))
+        );
+
+        // json and toml take precedence over yaml
+        let multiple = Path::new("tests/fixtures/config_search/multiple");
+        let data = build(multiple, None).unwrap();
+        assert_eq!(data.title, Some(String::from("TOML config")));
+
+        let data = build(
+            root,
+            Some(Data {
+                config: Some(multiple.join("mdpage.yml")),
+                ..Data::default()
+            }),
+        )
+        .unwrap();
+        assert_eq!(data.title, Some(String::from("YAML config")));
+
+        let res = build(
+            root,
+            Some(Data {
+                config: Some(multiple.join("missing.toml")),
+                ..Data::default()
+            }),
+        );
+        assert!(matches!(res, Err(Error::MissingFile { .. })));
+    }
+
+    #[test]
+    fn test_ignore() {
+        let root = Path::new("tests/fixtures/ignore");
+        let labels = |data: &Data| {
+            data.contents
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|c| c.label.clone().unwrap_or_default())
+                .collect::<Vec<String>>()
+        };
+
+        let mut data = Data {
+            exclude: Some(vec![
+                String::from("CHANGELOG"),
+                String::from("node_modules"),
+            ]),
+            ..Data::default()
+        };
+        data.init(root).unwrap();
+        assert_eq!(
+            labels(&data),
+            vec!["Intro", "", ".notes", "Notes", "", "guide", "Setup", ""]
+        );
+
+        data = Data {
+            include: Some(vec![String::from("guide/**")]),
+            ..Data::default()
+        };
+        data.init(root).unwrap();
+        assert_eq!(labels(&data), vec!["guide", "Setup", ""]);
+
+        data = Data {
+            exclude: Some(vec![String::from("[")]),
+            ..Data::default()
+        };
+        assert!(matches!(data.init(root), Err(Error::Pattern { .. })));
+    }
+
+    #[test]
+    fn test_sort() {
+        let root = Path::new("tests/fixtures/order");
+        let labels = |data: &Data| {
+            data.contents
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|c| c.label.clone().unwrap_or_default())
+                .collect::<Vec<String>>()
+        };
+
+        let mut data = Data::default();
+        data.init(root).unwrap();
+        assert_eq!(
+            labels(&data),
+            vec![
+                "01-intro",
+                "10-faq",
+                "2-setup",
+                "",
+                "10-api",
+                "Client",
+                "",
+                "3-guides",
+                "Deployment",
+                "Advanced",
+                "Basics",
+                ""
+            ]
+        );
+
+        data = Data {
+            sort: Some(SortOrder::Numeric),
+            ..Data::default()
+        };
+        data.init(root).unwrap();
+        assert_eq!(
+            labels(&data),
+            vec![
+                "intro",
+                "setup",
+                "faq",
+                "",
+                "guides",
+                "Deployment",
+                "Advanced",
+                "Basics",
+                "",
+                "api",
+                "Client",
+                ""
+            ]
+        );
+
+        data = Data {
+            sort: Some(SortOrder::Title),
+            ..Data::default()
+        };
+        data.init(root).unwrap();
+        assert_eq!(
+            labels(&data),
+            vec![
+                "01-intro",
+                "10-faq",
+                "2-setup",
+                "",
+                "10-api",
+                "Client",
+                "",
+                "3-guides",
+                "Deployment",
+                "Advanced",
+                "Basics",
+                ""
+            ]
+        );
+    }
+
+    #[test]
+    fn test_safe() {
+        let root = Path::new("tests/fixtures/safe");
+        let mut raw = Content::new(None);
+        raw.label = Some(String::from("Raw"));
+        raw.html = Some(String::from(r#"<a href="javascript:alert(5)">raw</a>"#));
+        let initial = Data {
+            safe: Some(true),
+            contents: Some(vec![Content::new(Some(PathBuf::from("page.html"))), raw]),
+            ..Data::default()
+        };
+
+        let data = build(root, Some(initial.clone())).unwrap();
+        assert_eq!(
+            data.main.unwrap().html.unwrap(),
+            "<h1>Safe</h1>\n&lt;script&gt;alert(\"main\")&lt;/script&gt;\n<p>A <a rel=\"noopener noreferrer\">link</a> and <b>bold</b> text.</p>\n"
+        );
+        let contents = data.contents.unwrap();
+        assert_eq!(
+            contents[0].html,
+            Some(String::from("<h1>Page</h1>\n<p><img src=\"logo.png\"></p>"))
+        );
+        assert_eq!(
+            contents[1].html,
+            Some(String::from(r#"<a rel="noopener noreferrer">raw</a>"#))
+        );
+        assert_eq!(
+            data.footer.unwrap().html,
+            Some(String::from("<div>Footer</div>\n"))
+        );
+
+        // the highlighted code keeps its classes while the styles of the markdown are removed
+        let mut code = Content::new(None);
+        code.label = Some(String::from("Code"));
+        code.markdown = Some(String::from(
+            "<span style=\"position:fixed\">overlay</span>\n\n```rust\nfn main() {}\n```",
+        ));
+        let highlighted = Data {
+            highlight: Some(true),
+            contents: Some(vec![code]),
+            ..initial.clone()
+        };
+        let html = build(root, Some(highlighted)).unwrap().contents.unwrap()[0]
+            .html
+            .clone()
+            .unwrap();
+        assert!(html.starts_with("<p><span>overlay</span></p>\n<pre class=\"hl-code\">"));
+        assert!(html.contains(r#"<span class="hl-storage hl-type hl-function hl-rust">fn</span>"#));
+        assert!(!html.contains("style="));
+
+        let data = build(root, None).unwrap();
+        assert!(data.main.unwrap().html.unwrap().contains("onmouseover"));
+
+        let initial = Data {
+            script: Some(String::from("alert(6)")),
+            ..initial
+        };
+        assert!(matches!(
+            build(root, Some(initial)),
+            Err(Error::Unsafe { .. })
+        ));
+    }
+
+    #[test]
+    fn test_build_files() {
+        let root = Path::new("tests/fixtures/files");
+        let file = |name: &str| Content {
+            file: Some(root.join(name)),
+            ..Content::default()
+        };
+
+        let data = build_files(vec![file("design.md")], None).unwrap();
+        assert_eq!(data.title, Some(String::from("Design")));
+        assert_eq!(data.full_page, Some(false));
+        let main = data.main.unwrap();
+        assert_eq!(main.label, Some(String::from("Design")));
+        assert_eq!(
+            main.description,
+            Some(String::from("The design of the feature"))
+        );
+        assert_eq!(
+            main.html,
+            Some(String::from("<h1>Design</h1>\n<p>The summary.</p>\n"))
+        );
+        let contents = data.contents.unwrap();
+        assert_eq!(
+            contents
+                .iter()
+                .map(|c| c.label.clone().unwrap())
+                .collect::<Vec<String>>(),
+            vec!["Goals", "Plan"]
+        );
+        assert!(contents[0].html.as_ref().unwrap().contains("Non goals"));
+        assert!(!contents[0].html.as_ref().unwrap().contains("The plan"));
+
+        let data = build_files(vec![file("design.md"), file("notes.md")], None).unwrap();
+        assert_eq!(data.title, Some(String::from("Design")));
+        assert!(data
+            .main
+            .unwrap()
+            .html
+            .unwrap()
+            .contains(r##"<a href="#doc-section-0">notes</a>"##));
+        let contents = data.contents.unwrap();
+        assert_eq!(contents.len(), 1);
+        assert_eq!(contents[0].label, Some(String::from("Notes")));
+
+        let stdin = Content {
+            markdown: Some(String::from("---\nlabel: Piped\n---\n# Title\n\nText.\n")),
+            ..Content::default()
+        };
+        let initial = Data {
+            title: Some(String::from("Custom")),
+            ..Data::default()
+        };
+        let data = build_files(vec![stdin], Some(initial)).unwrap();
+        assert_eq!(data.title, Some(String::from("Custom")));
+        let main = data.main.unwrap();
+        assert_eq!(main.label, Some(String::from("Piped")));
+        assert_eq!(
+            main.html,
+            Some(String::from("<h1>Title</h1>\n<p>Text.</p>\n"))
+        );
+        assert_eq!(data.contents, Some(Vec::new()));
+
+        assert!(matches!(
+            build_files(vec![file("missing.md")], None),
+            Err(Error::MissingFile { .. })
+        ));
+    }
+
+    #[test]
+    fn test_split() {
+        let root = Path::new("tests/fixtures/split");
+        let labels = |data: &Data| {
+            data.contents
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|c| c.label.clone().unwrap_or_default())
+                .collect::<Vec<String>>()
+        };
+
+        let data = build(root, None).unwrap();
+        assert_eq!(
+            labels(&data),
+            vec!["Legacy", "Install", "Usage", "Plain", "", "guide", "Part one", "Part two", ""]
+        );
+        let contents = data.contents.unwrap();
+        assert_eq!(
+            contents[1].html,
+            Some(String::from("<h2>Install</h2>\n<p>Install it.</p>\n"))
+        );
+        assert!(!contents[0].html.as_ref().unwrap().contains("Install"));
+
+        let initial = Data {
+            split: Some(SplitLevel::H2),
+            ..Data::default()
+        };
+        let data = build(root, Some(initial)).unwrap();
+        assert_eq!(
+            labels(&data),
+            vec![
+                "Legacy", "Install", "Usage", "Plain", "Details", "", "guide", "Part one",
+                "Part two", ""
+            ]
+        );
+        assert_eq!(
+            data.main.unwrap().html,
+            Some(String::from("<h1>Split</h1>\n<p>The main content.</p>\n"))
+        );
+    }
+
+    #[test]
+    fn test_split_links() {
+        let root = Path::new("tests/fixtures/split_links");
+        let main = |data: &Data| data.main.as_ref().unwrap().html.clone().unwrap();
+
+        let initial = Data {
+            toc: Some(true),
+            ..Data::default()
+        };
+        let data = build(root, Some(initial)).unwrap();
+        assert!(main(&data).contains(r##"<a href="#usage-running-it">running it</a>"##));
+        assert!(main(&data).contains(r##"<a href="#doc-section-0">the guide</a>"##));
+
+        let initial = Data {
+            multi_page: Some(true),
+            toc: Some(true),
+            ..Data::default()
+        };
+        let data = build(root, Some(initial)).unwrap();
+        assert!(main(&data).contains(r##"<a href="usage.html#usage-running-it">running it</a>"##));
+        assert!(main(&data).contains(r##"<a href="guide.html">the guide</a>"##));
+    }
+
+    #[test]
+    fn test_summary() {
+        let root = Path::new("tests/fixtures/book");
+
+        let data = build(root, None).unwrap();
+        assert_eq!(data.title, Some(String::from("The Book")));
+        assert_eq!(data.author, Some(String::from("Jane Doe, John Roe")));
+        assert_eq!(data.main, None);
+
+        let contents = data.contents.unwrap();
+        assert_eq!(
+            contents
+                .iter()
+                .map(|c| (c.label.clone().unwrap_or_default(), c.level))
+                .collect::<Vec<(String, Option<usize>)>>(),
+            vec![
+                (String::from("Introduction"), None),
+                (String::from("User Guide"), None),
+                (String::from("Installation"), None),
+                (String::from("From source"), Some(2)),
+                (String::new(), None),
+                (String::from("Contributors"), None),
+            ]
+        );
+        assert_eq!(contents[1].is_heading, Some(true));
+        assert_eq!(contents[4].is_break, Some(true));
+        assert!(contents[0]
+            .html
+            .as_ref()
+            .unwrap()
+            .contains(r##"<a href="#doc-section-2">installation</a>"##));
+
+        let initial = Data {
+            title: Some(String::from("Custom")),
+            ..Data::default()
+        };
+        let data = build(root, Some(initial)).unwrap();
+        assert_eq!(data.title, Some(String::from("Custom")));
+    }
 }
@@ -0,0 +1,204 @@
+//! Error type of the library.
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The errors that can occur when building and rendering the documentation.
+#[derive(Debug)]
+pub enum Error {
+    /// A file or path referenced by the data does not exist.
+    MissingFile { path: PathBuf, source: io::Error },
+    /// A file could not be read.
+    ReadFile { path: PathBuf, source: io::Error },
+    /// A directory could not be read.
+    ReadDir { path: PathBuf, source: io::Error },
+    /// A file or directory could not be written.
+    WriteFile { path: PathBuf, source: io::Error },
+    /// The config file could not be parsed.
+    Config {
+        path: PathBuf,
+        source: Box<dyn StdError + Send + Sync>,
+    },
+    /// The value of an environment variable setting a config property is not valid.
+    Env {
+        name: String,
+        source: Box<dyn StdError + Send + Sync>,
+    },
+    /// The front matter of a markdown file could not be parsed.
+    FrontMatter {
+        path: PathBuf,
+        source: Box<dyn StdError + Send + Sync>,
+    },
+    /// A template could not be parsed. The path is not set for the built-in templates.
+    Template {
+        path: Option<PathBuf>,
+        source: Box<handlebars::TemplateError>,
+    },
+    /// A template could not be rendered.
+    Render {
+        template: String,
+        source: Box<handlebars::RenderError>,
+    },
+    /// The config property is not allowed in safe mode.
+    Unsafe { property: String },
+    /// A glob pattern of the included or excluded files is not valid.
+    Pattern {
+        pattern: String,
+        source: globset::Error,
+    },
+    /// The rendered output could not be written.
+    Output { source: io::Error },
+    /// The sources could not be watched for changes.
+    Watch {
+        path: PathBuf,
+        source: notify::Error,
+    },
+    /// The server could not be started.
+    Serve {
+        address: String,
+        source: Box<dyn StdError + Send + Sync>,
+    },
+}
+
+/// The result type of the library.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Create the error for a file that could not be opened or read,
+    /// distinguishing the files that do not exist.
+    pub(crate) fn read_file(path: &Path, source: io::Error) -> Error {
+        let path = path.to_path_buf();
+
+        if source.kind() == io::ErrorKind::NotFound {
+            Error::MissingFile { path, source }
+        } else {
+            Error::ReadFile { path, source }
+        }
+    }
+
+    /// The path the error relates to, if any.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Error::MissingFile { path, .. }
+            | Error::ReadFile { path, .. }
+            | Error::ReadDir { path, .. }
+            | Error::WriteFile { path, .. }
+            | Error::Config { path, .. }
+            | Error::FrontMatter { path, .. }
+            | Error::Watch { path, .. } => Some(path),
+            Error::Template { path, .. } => path.as_deref(),
+            Error::Render { .. }
+            | Error::Env { .. }
+            | Error::Unsafe { .. }
+            | Error::Pattern { .. }
+            | Error::Output { .. }
+            | Error::Serve { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::MissingFile { path, source } => {
+                write!(f, "could not find: {}. {}", path.display(), source)
+            }
+            Error::ReadFile { path, source } => {
+                write!(f, "Error reading file: {}. {}", path.display(), source)
+            }
+            Error::ReadDir { path, source } => {
+                write!(f, "Error reading dir: {}. {}", path.display(), source)
+            }
+            Error::WriteFile { path, source } => {
+                write!(f, "could not write: {}. {}", path.display(), source)
+            }
+            Error::Config { path, source } => {
+                write!(f, "Error reading config: {}. {}", path.display(), source)
+            }
+            Error::Env { name, source } => {
+                write!(f, "invalid environment variable: {}. {}", name, source)
+            }
+            Error::FrontMatter { path, source } => {
+                write!(
+                    f,
+                    "Error reading front matter: {}. {}",
+                    path.display(),
+                    source
+                )
+            }
+            Error::Template {
+                path: Some(path),
+                source,
+            } => write!(f, "Error reading template: {}. {}", path.display(), source),
+            Error::Template { path: None, source } => {
+                write!(f, "Error reading template. {}", source)
+            }
+            Error::Render { template, source } => {
+                write!(f, "Error rendering template: {}. {}", template, source)
+            }
+            Error::Unsafe { property } => {
+                write!(
+                    f,
+                    "the {} config property is not allowed in safe mode",
+                    property
+                )
+            }
+            Error::Pattern { pattern, source } => {
+                write!(f, "invalid pattern: {}. {}", pattern, source)
+            }
+            Error::Output { source } => write!(f, "Error writing output. {}", source),
+            Error::Watch { path, source } => {
+                write!(f, "could not watch path: {}. {}", path.display(), source)
+            }
+            Error::Serve { address, source } => {
+                write!(f, "could not start server on: {}. {}", address, source)
+            }
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Error::MissingFile { source, .. }
+            | Error::ReadFile { source, .. }
+            | Error::ReadDir { source, .. }
+            | Error::WriteFile { source, .. }
+            | Error::Output { source } => Some(source),
+            Error::Config { source, .. }
+            | Error::Env { source, .. }
+            | Error::FrontMatter { source, .. }
+            | Error::Serve { source, .. } => Some(source.as_ref()),
+            Error::Template { source, .. } => Some(source.as_ref()),
+            Error::Render { source, .. } => Some(source.as_ref()),
+            Error::Watch { source, .. } => Some(source),
+            Error::Pattern { source, .. } => Some(source),
+            Error::Unsafe { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_file() {
+        let err = Error::read_file(
+            Path::new("missing.md"),
+            io::Error::new(io::ErrorKind::NotFound, "not found"),
+        );
+        assert!(matches!(err, Error::MissingFile { .. }));
+        assert_eq!(err.path(), Some(Path::new("missing.md")));
+        assert_eq!(err.to_string(), "could not find: missing.md. not found");
+        assert!(err.source().is_some());
+
+        let err = Error::read_file(
+            Path::new("locked.md"),
+            io::Error::new(io::ErrorKind::PermissionDenied, "denied"),
+        );
+        assert!(matches!(err, Error::ReadFile { .. }));
+    }
+}
@@ -0,0 +1,170 @@
+//! Filtering of the files and directories discovered for the contents.
+
+use std::fs::DirEntry;
+use std::path::{Path, PathBuf};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+
+use crate::error::{Error, Result};
+
+/// The name of the file in the root directory listing the files and directories to ignore, in the gitignore syntax.
+pub const IGNORE_FILE: &str = ".mdpageignore";
+
+/// The filter of the discovered files and directories, using the include and exclude glob patterns
+/// and the ignore file.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    base: PathBuf,
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+    ignore: Option<Gitignore>,
+    skip_hidden: bool,
+}
+
+impl PathFilter {
+    /// Create the filter with the patterns relative to the base directory.
+    /// When there are include patterns only the matching files are discovered.
+    pub fn new(base: &Path, include: &[String], exclude: &[String]) -> Result<PathFilter> {
+        Ok(PathFilter {
+            base: base.to_path_buf(),
+            include: glob_set(include)?,
+            exclude: glob_set(exclude)?,
+            ignore: None,
+            skip_hidden: false,
+        })
+    }
+
+    /// Leave out the hidden directories, unless they are explicitly re-included in the ignore file with a `!` pattern.
+    pub fn skip_hidden(mut self) -> PathFilter {
+        self.skip_hidden = true;
+        self
+    }
+
+    /// Add the patterns of the ignore file in the base directory, if there is one.
+    pub fn with_ignore_file(mut self) -> Result<PathFilter> {
+        let path = self.base.join(IGNORE_FILE);
+        if !path.is_file() {
+            return Ok(self);
+        }
+
+        let config_error = |err: ignore::Error| Error::Config {
+            path: path.clone(),
+            source: Box::new(err),
+        };
+
+        let mut builder = GitignoreBuilder::new(&self.base);
+        if let Some(err) = builder.add(&path) {
+            return Err(config_error(err));
+        }
+
+        self.ignore = Some(builder.build().map_err(config_error)?);
+        Ok(self)
+    }
+
+    /// Whether the file or directory is left out of the discovered contents.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let rel = path.strip_prefix(&self.base).unwrap_or(path);
+
+        let mut whitelisted = false;
+        if let Some(ignore) = self.ignore.as_ref() {
+            match ignore.matched(rel, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => whitelisted = true,
+                Match::None => {}
+            }
+        }
+
+        if is_dir && self.skip_hidden && !whitelisted && is_hidden(path) {
+            return true;
+        }
+
+        if let Some(exclude) = self.exclude.as_ref() {
+            if matches(exclude, rel, path) {
+                return true;
+            }
+        }
+
+        match self.include.as_ref() {
+            Some(include) if !is_dir => !matches(include, rel, path),
+            _ => false,
+        }
+    }
+
+    /// Whether the directory entry is left out of the discovered contents.
+    pub fn is_ignored_entry(&self, entry: &DirEntry) -> bool {
+        let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+        self.is_ignored(&entry.path(), is_dir)
+    }
+}
+
+/// Build the set of the glob patterns, or none if there are no patterns.
+fn glob_set(patterns: &[String]) -> Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|source| Error::Pattern {
+            pattern: pattern.clone(),
+            source,
+        })?;
+        builder.add(glob);
+    }
+
+    builder.build().map(Some).map_err(|source| Error::Pattern {
+        pattern: patterns.join(", "),
+        source,
+    })
+}
+
+/// Whether the patterns match the relative path, the name, or the name without the extension,
+/// so that a pattern such as `CHANGELOG` or `node_modules` matches at any depth.
+fn matches(set: &GlobSet, rel: &Path, path: &Path) -> bool {
+    set.is_match(rel)
+        || path.file_name().is_some_and(|name| set.is_match(name))
+        || path.file_stem().is_some_and(|stem| set.is_match(stem))
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.') && name != "." && name != "..")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_filter() {
+        let root = Path::new("docs");
+        let filter = PathFilter::new(
+            root,
+            &[],
+            &[String::from("CHANGELOG"), String::from("drafts/**")],
+        )
+        .unwrap()
+        .skip_hidden();
+
+        assert!(!filter.is_ignored(&root.join("guide.md"), false));
+        assert!(filter.is_ignored(&root.join("CHANGELOG.md"), false));
+        assert!(filter.is_ignored(&root.join("api/CHANGELOG.md"), false));
+        assert!(filter.is_ignored(&root.join("drafts/wip.md"), false));
+        assert!(filter.is_ignored(&root.join(".github"), true));
+        assert!(!filter.is_ignored(&root.join("api"), true));
+
+        let filter = PathFilter::new(root, &[String::from("guide/*.md")], &[]).unwrap();
+        assert!(filter.is_ignored(&root.join("intro.md"), false));
+        assert!(!filter.is_ignored(&root.join("guide/intro.md"), false));
+        assert!(!filter.is_ignored(&root.join("guide"), true));
+        assert!(!filter.is_ignored(&root.join(".github"), true));
+
+        match PathFilter::new(root, &[String::from("a[b")], &[]) {
+            Err(Error::Pattern { pattern, .. }) => assert_eq!(pattern, "a[b"),
+            res => panic!("expected pattern error, got: {:?}", res),
+        }
+    }
+}
@@ -0,0 +1,191 @@
+//! Front matter block at the start of markdown files with the metadata of the content.
+
+use std::error::Error as StdError;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::content::Content;
+use crate::error::{Error, Result};
+use crate::split::SplitLevel;
+
+/// The metadata of the content set in the front matter.
+/// The block is either YAML delimited by `---` lines or TOML delimited by `+++` lines.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct FrontMatter {
+    /// The label of the content in the menu.
+    pub label: Option<String>,
+    /// The weight used to order the content, lower weights come first.
+    pub weight: Option<i64>,
+    /// Whether the content is left out of the menu.
+    pub hidden: Option<bool>,
+    /// Whether the content is a draft and is left out of the document.
+    pub draft: Option<bool>,
+    /// The heading level to split the content at.
+    pub split: Option<SplitLevel>,
+    /// The description of the content.
+    pub description: Option<String>,
+    /// Any other custom fields made available to the templates.
+    pub extra: Option<Map<String, Value>>,
+}
+
+impl FrontMatter {
+    /// Set the properties of the content that are not already set.
+    pub fn fill(self, c: &mut Content) {
+        if c.label.is_none() {
+            c.label = self.label;
+        }
+        if c.weight.is_none() {
+            c.weight = self.weight;
+        }
+        if c.hidden.is_none() {
+            c.hidden = self.hidden;
+        }
+        if c.draft.is_none() {
+            c.draft = self.draft;
+        }
+        if c.split.is_none() {
+            c.split = self.split;
+        }
+        if c.description.is_none() {
+            c.description = self.description;
+        }
+        if c.extra.is_none() {
+            c.extra = self.extra;
+        }
+    }
+}
+
+/// Split the front matter block from the rest of the markdown.
+/// Returns the front matter source along with the delimiter, and the remaining markdown.
+fn split(markdown: &str) -> Option<(&str, &str, &str)> {
+    let delimiter = ["---", "+++"].iter().find(|d| markdown.starts_with(*d))?;
+
+    let first_line = markdown.find('\n')?;
+    if markdown[..first_line].trim_end() != *delimiter {
+        return None;
+    }
+
+    let mut pos = first_line + 1;
+    while pos <= markdown.len() {
+        let end = markdown[pos..]
+            .find('\n')
+            .map(|e| pos + e)
+            .unwrap_or_else(|| markdown.len());
+
+        if markdown[pos..end].trim_end() == *delimiter {
+            let rest = markdown.get(end + 1..).unwrap_or_default();
+            return Some((delimiter, &markdown[first_line + 1..pos], rest));
+        }
+
+        pos = end + 1;
+    }
+
+    None
+}
+
+/// Parse the front matter of the markdown if present.
+/// Returns the front matter and the markdown with the front matter block removed.
+/// A block that is not a YAML or TOML mapping, such as text between two horizontal rules,
+/// is left in the markdown with a warning.
+pub fn parse_front_matter(
+    markdown: &str,
+) -> std::result::Result<(Option<FrontMatter>, &str), Box<dyn StdError + Send + Sync>> {
+    let (delimiter, source, rest) = match split(markdown) {
+        Some(parts) => parts,
+        None => return Ok((None, markdown)),
+    };
+
+    let fm = if source.trim().is_empty() {
+        FrontMatter::default()
+    } else if delimiter == "---" {
+        match serde_yaml::from_str::<serde_yaml::Value>(source) {
+            Ok(value @ serde_yaml::Value::Mapping(_)) => serde_yaml::from_value(value)?,
+            _ => return Ok(not_front_matter(markdown)),
+        }
+    } else {
+        match toml::from_str::<toml::Value>(source) {
+            Ok(value) => value.try_into()?,
+            Err(_) => return Ok(not_front_matter(markdown)),
+        }
+    };
+
+    Ok((Some(fm), rest))
+}
+
+fn not_front_matter(markdown: &str) -> (Option<FrontMatter>, &str) {
+    warn!("the block at the start of the markdown is not a front matter mapping and is kept as markdown");
+    (None, markdown)
+}
+
+/// Read the front matter of the markdown file if present.
+pub fn read_front_matter(path: &Path) -> Result<Option<FrontMatter>> {
+    let markdown = fs::read_to_string(path).map_err(|err| Error::read_file(path, err))?;
+
+    parse_front_matter(&markdown)
+        .map(|(fm, _)| fm)
+        .map_err(|source| Error::FrontMatter {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_front_matter() {
+        // none
+        let (fm, rest) = parse_front_matter("# Title\n\n---\n").unwrap();
+        assert_eq!(fm, None);
+        assert_eq!(rest, "# Title\n\n---\n");
+
+        // yaml
+        let (fm, rest) = parse_front_matter(
+            "---\nlabel: Setup\nweight: 2\nhidden: true\nextra:\n  icon: gear\n---\n# Title\n",
+        )
+        .unwrap();
+        let fm = fm.unwrap();
+        assert_eq!(fm.label, Some(String::from("Setup")));
+        assert_eq!(fm.weight, Some(2));
+        assert_eq!(fm.hidden, Some(true));
+        assert_eq!(fm.draft, None);
+        assert_eq!(
+            fm.extra.unwrap().get("icon"),
+            Some(&Value::String(String::from("gear")))
+        );
+        assert_eq!(rest, "# Title\n");
+
+        // toml
+        let (fm, rest) =
+            parse_front_matter("+++\r\ndraft = true\r\ndescription = \"Notes\"\r\n+++\r\nText")
+                .unwrap();
+        let fm = fm.unwrap();
+        assert_eq!(fm.draft, Some(true));
+        assert_eq!(fm.description, Some(String::from("Notes")));
+        assert_eq!(rest, "Text");
+
+        // empty and unterminated
+        let (fm, rest) = parse_front_matter("---\n---").unwrap();
+        assert_eq!(fm, Some(FrontMatter::default()));
+        assert_eq!(rest, "");
+        let (fm, _) = parse_front_matter("---\nlabel: Setup\n").unwrap();
+        assert_eq!(fm, None);
+
+        // invalid values
+        assert!(parse_front_matter("---\nweight: heavy\n---\n").is_err());
+        assert!(parse_front_matter("+++\nweight = \"heavy\"\n+++\n").is_err());
+
+        // horizontal rules around text rather than a mapping
+        let markdown = "---\n\nSome text.\n\n---\n\n# Title\n";
+        assert_eq!(parse_front_matter(markdown).unwrap(), (None, markdown));
+        let markdown = "---\nweight: [\n---\n";
+        assert_eq!(parse_front_matter(markdown).unwrap(), (None, markdown));
+        let markdown = "+++\nSome text.\n+++\n";
+        assert_eq!(parse_front_matter(markdown).unwrap(), (None, markdown));
+    }
+}
@@ -0,0 +1,161 @@
+//! Syntax highlighting of the fenced code blocks at build time.
+
+use std::sync::OnceLock;
+
+use comrak::nodes::{AstNode, NodeHtmlBlock, NodeValue};
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::html::{css_for_theme_with_class_style, ClassStyle, ClassedHTMLGenerator};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+/// The theme used when none is specified.
+pub const DEFAULT_THEME: &str = "InspiredGitHub";
+
+/// The prefix of the classes of the highlighted code, so they do not clash with the classes of the page.
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAX_SET: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAX_SET.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn theme_set() -> &'static ThemeSet {
+    static THEME_SET: OnceLock<ThemeSet> = OnceLock::new();
+    THEME_SET.get_or_init(ThemeSet::load_defaults)
+}
+
+/// The names of the available themes.
+fn theme_names() -> Vec<&'static str> {
+    theme_set().themes.keys().map(String::as_str).collect()
+}
+
+/// Find the theme by name, falling back to the default theme with a warning if it does not exist.
+fn find_theme(name: &str) -> &'static Theme {
+    let themes = &theme_set().themes;
+
+    match themes.get(name) {
+        Some(theme) => theme,
+        None => {
+            warn!(
+                "unknown highlight theme: {}. Using {}, available themes: {}",
+                name,
+                DEFAULT_THEME,
+                theme_names().join(", ")
+            );
+            &themes[DEFAULT_THEME]
+        }
+    }
+}
+
+/// Generate the stylesheet of the theme for the classes of the highlighted code.
+pub fn highlight_css(theme: &str) -> String {
+    css_for_theme_with_class_style(find_theme(theme), CLASS_STYLE).unwrap_or_default()
+}
+
+/// Highlight the code in the language, with a class for each of the scopes, which are styled by `highlight_css`.
+/// Returns `None` if the language is not known.
+pub fn highlight_code(code: &str, lang: &str) -> Option<String> {
+    let ss = syntax_set();
+    let syntax = ss.find_syntax_by_token(lang)?;
+
+    let mut generator = ClassedHTMLGenerator::new_with_class_style(syntax, ss, CLASS_STYLE);
+    for line in LinesWithEndings::from(code) {
+        generator
+            .parse_html_for_line_which_includes_newline(line)
+            .ok()?;
+    }
+
+    Some(format!(
+        "<pre class=\"hl-code\"><code class=\"language-{}\">{}</code></pre>\n",
+        escape(lang),
+        generator.finalize()
+    ))
+}
+
+/// Replace the fenced code blocks in the document with the highlighted HTML.
+/// Code blocks without a language, or in a language that is not known, are left as they are.
+pub fn highlight_blocks<'a>(root: &'a AstNode<'a>) {
+    for node in root.descendants() {
+        let highlighted = match node.data.borrow().value {
+            NodeValue::CodeBlock(ref block) => {
+                let info = String::from_utf8_lossy(&block.info);
+                let lang = info.split_whitespace().next().unwrap_or_default();
+                if lang.is_empty() {
+                    continue;
+                }
+
+                highlight_code(&String::from_utf8_lossy(&block.literal), lang)
+            }
+            _ => continue,
+        };
+
+        if let Some(html) = highlighted {
+            node.data.borrow_mut().value = NodeValue::HtmlBlock(NodeHtmlBlock {
+                block_type: 0,
+                literal: html.into_bytes(),
+            });
+        }
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use comrak::{format_html, parse_document, Arena, ComrakOptions};
+
+    #[test]
+    fn test_highlight_code() {
+        let html = highlight_code("fn main() {}\n", "rust").unwrap();
+        assert!(html.starts_with(r#"<pre class="hl-code"><code class="language-rust">"#));
+        assert!(html.contains(r#"<span class="hl-storage hl-type hl-function hl-rust">fn</span>"#));
+        assert!(!html.contains("style="));
+        assert!(html.ends_with("</code></pre>\n"));
+
+        // by extension
+        assert!(highlight_code("x = 1\n", "py").is_some());
+
+        assert!(highlight_code("x", "no-such-language").is_none());
+    }
+
+    #[test]
+    fn test_highlight_css() {
+        let css = highlight_css(DEFAULT_THEME);
+        assert!(css.contains(".hl-code {\n color: #323232;\n background-color: #ffffff;\n}"));
+        assert!(css.contains(".hl-storage"));
+
+        // unknown themes fall back to the default theme
+        assert_eq!(highlight_css("unknown"), css);
+        assert!(highlight_css("base16-ocean.dark").contains("background-color: #2b303b;"));
+    }
+
+    #[test]
+    fn test_highlight_blocks() {
+        let options = ComrakOptions {
+            unsafe_: true,
+            ..ComrakOptions::default()
+        };
+        let arena = Arena::new();
+        let root = parse_document(
+            &arena,
+            "```rust\nlet a = 1;\n```\n\n```\nplain\n```\n\n    indented\n",
+            &options,
+        );
+        highlight_blocks(root);
+
+        let mut html = Vec::new();
+        format_html(root, &options, &mut html).unwrap();
+        let html = String::from_utf8(html).unwrap();
+
+        assert!(html.starts_with(r#"<pre class="hl-code"><code class="language-rust"><span"#));
+        assert!(html.contains("<pre><code>plain\n</code></pre>"));
+        assert!(html.contains("<pre><code>indented\n</code></pre>"));
+    }
+}
@@ -4,12 +4,44 @@ extern crate handlebars;
 #[macro_use]
 extern crate log;
 
+mod assets;
+mod check;
+mod config;
 mod content;
 mod data;
+mod error;
+mod filter;
+mod front_matter;
+mod highlight;
+mod links;
+mod markdown;
+mod order;
+mod sanitize;
+mod search;
+mod serve;
+mod split;
+mod summary;
+mod toc;
 mod utils;
+mod watch;
 mod writer;
 
+pub use check::{check, Problem};
+pub use config::{resolve_config, Config, Layer};
 pub use content::Content;
 pub use data::build;
+pub use data::build_files;
 pub use data::Data;
+pub use data::DirConfig;
+pub use data::{Link, LinkType};
+pub use error::Error;
+pub use error::Result;
+pub use markdown::MarkdownOptions;
+pub use order::SortOrder;
+pub use serve::serve;
+pub use split::SplitLevel;
+pub use toc::TocEntry;
+pub use watch::watch;
+pub use writer::render_pages;
 pub use writer::write_data;
+pub use writer::write_pages;
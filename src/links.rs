@@ -0,0 +1,191 @@
+//! Rewriting of the links between the markdown files into links within the generated document.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::assets::{find_attr, is_local};
+use crate::utils::percent_decode;
+
+/// The location of a content within the generated document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkTarget {
+    /// The page file of the content in multi page mode, or empty for the single document.
+    pub page: String,
+    /// The ID of the element of the content within the page.
+    pub anchor: String,
+    /// The prefix of the IDs of the headings within the content, or none if the headings have no IDs.
+    pub header_prefix: Option<String>,
+    /// The IDs of the headings within the content without the prefix, the fragments that point into the content.
+    pub headings: Vec<String>,
+}
+
+impl LinkTarget {
+    /// Generate the URL of the content, optionally pointing to a heading within the content.
+    /// The heading ID is the fragment with the header prefix of the content, as generated by comrak.
+    /// Without header IDs the URL points to the content itself.
+    pub fn url(&self, fragment: Option<&str>) -> String {
+        let fragment = fragment.filter(|f| !f.is_empty());
+        match (fragment, self.header_prefix.as_ref()) {
+            (Some(fragment), Some(prefix)) => format!("{}#{}{}", self.page, prefix, fragment),
+            _ if self.page.is_empty() => format!("#{}", self.anchor),
+            _ => self.page.clone(),
+        }
+    }
+}
+
+/// Rewrite the `href` attributes in the HTML that point to the files of other contents
+/// into links to the contents within the document.
+/// Relative URLs are resolved against the base directory, and the targets are keyed by the canonical file path.
+/// A file split into several contents has a target for each of them, in order.
+pub fn rewrite_html_links(
+    html: &str,
+    base: &Path,
+    targets: &HashMap<PathBuf, Vec<LinkTarget>>,
+) -> String {
+    let mut res = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(pos) = find_attr(rest, "href") {
+        // position right after the opening quote
+        let start = pos + 6;
+        let quote = rest.as_bytes()[pos + 5] as char;
+
+        res.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let end = match rest.find(quote) {
+            Some(end) => end,
+            None => break,
+        };
+
+        let url = &rest[..end];
+        match link_target(url, base, targets) {
+            Some(target) => res.push_str(&escape_url(&target)),
+            None => res.push_str(url),
+        }
+
+        rest = &rest[end..];
+    }
+
+    res.push_str(rest);
+    res
+}
+
+/// Find the target URL of the link if it points to the file of one of the contents.
+/// The fragment selects the content of the file that has the heading, otherwise the first content.
+fn link_target(
+    url: &str,
+    base: &Path,
+    targets: &HashMap<PathBuf, Vec<LinkTarget>>,
+) -> Option<String> {
+    // the URLs in the HTML have the ampersands escaped
+    let url = url.replace("&amp;", "&");
+    if !is_local(&url) {
+        return None;
+    }
+
+    let (path, fragment) = match url.find('#') {
+        Some(pos) => (&url[..pos], Some(&url[pos + 1..])),
+        None => (url.as_str(), None),
+    };
+    let path = path.split('?').next().unwrap_or_default();
+    if path.is_empty() {
+        return None;
+    }
+
+    let file = base.join(percent_decode(path)).canonicalize().ok()?;
+    let fragment = fragment.map(percent_decode);
+
+    let file_targets = targets.get(&file)?;
+    let target = fragment
+        .as_ref()
+        .and_then(|f| file_targets.iter().find(|t| t.headings.contains(f)))
+        .or_else(|| file_targets.first())?;
+
+    Some(target.url(fragment.as_deref()))
+}
+
+/// Escape the URL to be used within a quoted attribute.
+fn escape_url(url: &str) -> String {
+    url.replace('&', "&amp;")
+        .replace(' ', "%20")
+        .replace('"', "%22")
+        .replace('\'', "%27")
+        .replace('<', "%3C")
+        .replace('>', "%3E")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_html_links() {
+        let base = Path::new("tests/fixtures/links/guide");
+
+        let mut targets = HashMap::new();
+        targets.insert(
+            Path::new("tests/fixtures/links/reference/config.md")
+                .canonicalize()
+                .unwrap(),
+            vec![LinkTarget {
+                page: String::new(),
+                anchor: String::from("doc-section-4"),
+                header_prefix: Some(String::from("config-reference-")),
+                headings: vec![String::from("config-reference")],
+            }],
+        );
+        targets.insert(
+            Path::new("tests/fixtures/links/guide/install.md")
+                .canonicalize()
+                .unwrap(),
+            vec![LinkTarget {
+                page: String::from("install.html"),
+                anchor: String::from("doc-section-1"),
+                header_prefix: None,
+                headings: Vec::new(),
+            }],
+        );
+
+        assert_eq!(
+            rewrite_html_links(
+                r#"<a href="../reference/config.md">config</a> <a href='install.md#step%201'>step</a>"#,
+                base,
+                &targets
+            ),
+            r##"<a href="#doc-section-4">config</a> <a href='install.html'>step</a>"##
+        );
+
+        assert_eq!(
+            rewrite_html_links(
+                r#"<a href="../reference/config.md?v=1#links">links</a><a href="install.md">install</a>"#,
+                base,
+                &targets
+            ),
+            r##"<a href="#config-reference-links">links</a><a href="install.html">install</a>"##
+        );
+
+        // external, anchors, missing files and files of no content are left alone
+        let html = r##"<a href="https://example.com/config.md">a</a><a href="#top">b</a><a href="missing.md">c</a><a href="../index.md">d</a><p>href="install.md"</p>"##;
+        assert_eq!(rewrite_html_links(html, base, &targets), html);
+
+        // the fragments of a split file point to the content with the heading
+        let config = Path::new("tests/fixtures/links/reference/config.md")
+            .canonicalize()
+            .unwrap();
+        targets.get_mut(&config).unwrap().push(LinkTarget {
+            page: String::from("links.html"),
+            anchor: String::from("doc-section-5"),
+            header_prefix: Some(String::from("links-")),
+            headings: vec![String::from("links"), String::from("relative")],
+        });
+        assert_eq!(
+            rewrite_html_links(
+                r#"<a href="../reference/config.md#relative">a</a><a href="../reference/config.md#other">b</a><a href="../reference/config.md">c</a>"#,
+                base,
+                &targets
+            ),
+            r##"<a href="links.html#links-relative">a</a><a href="#config-reference-other">b</a><a href="#doc-section-4">c</a>"##
+        );
+    }
+}
@@ -2,14 +2,76 @@
 extern crate log;
 
 use std::fs::File;
-use std::path::PathBuf;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 
 use env_logger::Env;
+use structopt::clap::AppSettings;
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
 /// Generate simple documentation
+#[structopt(setting = AppSettings::SubcommandsNegateReqs)]
 struct Args {
+    #[structopt(flatten)]
+    build: BuildArgs,
+
+    /// The output file, or the output directory for multi page documentation. Use - for the standard output
+    #[structopt(short, long)]
+    output: Option<PathBuf>,
+
+    /// Keep running and rebuild the output when the sources change
+    #[structopt(short, long, takes_value = false)]
+    watch: bool,
+
+    /// Path for the directory containing data, or the markdown files to render. Use - for the standard input
+    #[structopt(required = true)]
+    paths: Vec<PathBuf>,
+
+    #[structopt(subcommand)]
+    cmd: Option<Command>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Serve the documentation locally and reload the browser when the sources change
+    Serve {
+        #[structopt(flatten)]
+        build: BuildArgs,
+
+        /// The address to listen on
+        #[structopt(long, default_value = "127.0.0.1")]
+        host: String,
+
+        /// The port to listen on
+        #[structopt(short, long, default_value = "3000")]
+        port: u16,
+
+        /// Path for the directory containing data
+        path: PathBuf,
+    },
+    /// Check the documentation for missing files, broken links and other problems without rendering
+    Check {
+        #[structopt(flatten)]
+        build: BuildArgs,
+
+        /// Path for the directory containing data
+        path: PathBuf,
+    },
+    /// Print the resolved configuration and whether each value comes from the defaults, the config file,
+    /// the environment or the command line
+    Config {
+        #[structopt(flatten)]
+        build: BuildArgs,
+
+        /// Path for the directory containing data
+        path: PathBuf,
+    },
+}
+
+// The options used to build the data, common to all commands.
+#[derive(Debug, StructOpt)]
+struct BuildArgs {
     /// Title of the document
     #[structopt(long)]
     title: Option<String>,
@@ -18,51 +80,382 @@ struct Args {
     #[structopt(long)]
     subtitle: Option<String>,
 
+    /// Author used in the metadata of the document
+    #[structopt(long)]
+    author: Option<String>,
+
+    /// The favicon link
+    #[structopt(long)]
+    icon: Option<String>,
+
+    /// Markdown file of the main content used for the front page
+    #[structopt(long)]
+    main: Option<PathBuf>,
+
+    /// Markdown file of the custom header content
+    #[structopt(long)]
+    header: Option<PathBuf>,
+
+    /// Markdown file of the custom footer content
+    #[structopt(long)]
+    footer: Option<PathBuf>,
+
+    /// CSS file with the custom style added to the document
+    #[structopt(long)]
+    style_file: Option<PathBuf>,
+
+    /// JavaScript file with the custom script added to the document
+    #[structopt(long)]
+    script_file: Option<PathBuf>,
+
+    /// Style or script link added to the head of the document, as style:URL or script:URL.
+    /// Can be repeated
+    #[structopt(
+        long = "link",
+        value_name = "type:url",
+        number_of_values = 1,
+        parse(try_from_str = parse_link)
+    )]
+    links: Vec<mdpage::Link>,
+
     /// Generate full page documentation
     #[structopt(long, takes_value = false)]
     full_page: bool,
 
-    /// The output file
+    /// Generate a separate page for each of the contents
+    #[structopt(long, takes_value = false)]
+    multi_page: bool,
+
+    /// Generate a document without any external style and script dependencies
+    #[structopt(long, takes_value = false)]
+    self_contained: bool,
+
+    /// Add a table of contents of the content headings to the menu
+    #[structopt(long, takes_value = false)]
+    toc: bool,
+
+    /// Embed the local images and other assets as data URIs
+    #[structopt(long, takes_value = false)]
+    embed_assets: bool,
+
+    /// Maximum size in bytes of the assets to embed
+    #[structopt(long)]
+    embed_max_size: Option<u64>,
+
+    /// Add a search box with a full text search of the contents
+    #[structopt(long, takes_value = false)]
+    search: bool,
+
+    /// Highlight the syntax of the fenced code blocks
+    #[structopt(long, takes_value = false)]
+    highlight: bool,
+
+    /// Theme used to highlight the code blocks
+    #[structopt(long)]
+    highlight_theme: Option<String>,
+
+    /// Sanitize the rendered HTML and reject the inline script
+    #[structopt(long, takes_value = false)]
+    safe: bool,
+
+    /// Maximum depth of the subdirectories to discover contents in
+    #[structopt(long)]
+    max_depth: Option<usize>,
+
+    /// Order of the discovered contents: file, natural, numeric or title
+    #[structopt(long)]
+    sort: Option<mdpage::SortOrder>,
+
+    /// Heading level to split the markdown contents at into separate menu entries: h1 or h2
+    #[structopt(long)]
+    split: Option<mdpage::SplitLevel>,
+
+    /// Glob pattern of the files to discover. Can be repeated
+    #[structopt(long, number_of_values = 1)]
+    include: Vec<String>,
+
+    /// Glob pattern of the files and directories to leave out. Can be repeated
+    #[structopt(long, number_of_values = 1)]
+    exclude: Vec<String>,
+
+    /// Directory with custom templates overriding the built-in ones
+    #[structopt(long)]
+    templates: Option<PathBuf>,
+
+    /// Config file to use instead of the one in the root directory
     #[structopt(short, long)]
-    output: Option<PathBuf>,
+    config: Option<PathBuf>,
 
-    /// Path for the directory containing data
-    path: PathBuf,
+    /// Look for the config file in the parent directories if there is none in the root directory
+    #[structopt(long, takes_value = false)]
+    search_parents: bool,
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    env_logger::from_env(Env::default().default_filter_or("warn")).init();
+impl BuildArgs {
+    fn initial(&self) -> mdpage::Result<mdpage::Data> {
+        Ok(mdpage::Data {
+            title: self.title.clone(),
+            subtitle: self.subtitle.clone(),
+            author: self.author.clone(),
+            icon: self.icon.clone(),
+            main: self.main.as_deref().map(content_file),
+            header: self.header.as_deref().map(content_file),
+            footer: self.footer.as_deref().map(content_file),
+            style: self.style_file.as_deref().map(read_file).transpose()?,
+            script: self.script_file.as_deref().map(read_file).transpose()?,
+            links: non_empty(&self.links),
+            full_page: flag(self.full_page),
+            multi_page: flag(self.multi_page),
+            self_contained: flag(self.self_contained),
+            toc: flag(self.toc),
+            embed_assets: flag(self.embed_assets),
+            embed_max_size: self.embed_max_size,
+            search: flag(self.search),
+            highlight: flag(self.highlight),
+            highlight_theme: self.highlight_theme.clone(),
+            safe: flag(self.safe),
+            max_depth: self.max_depth,
+            sort: self.sort,
+            split: self.split,
+            include: non_empty(&self.include),
+            exclude: non_empty(&self.exclude),
+            template_dir: self.templates.as_deref().map(canonical_path),
+            config: self.config.clone(),
+            search_parents: flag(self.search_parents),
+            ..mdpage::Data::default()
+        })
+    }
+}
+
+// The paths given in the command line are relative to the current directory rather than the root.
+fn canonical_path(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn content_file(path: &Path) -> mdpage::Content {
+    mdpage::Content {
+        file: Some(canonical_path(path)),
+        ..mdpage::Content::default()
+    }
+}
+
+fn read_file(path: &Path) -> mdpage::Result<String> {
+    std::fs::read_to_string(path).map_err(|source| mdpage::Error::ReadFile {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn parse_link(s: &str) -> Result<mdpage::Link, String> {
+    let (link_type, src) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected style:URL or script:URL, got: {}", s))?;
+
+    let link_type = link_type.parse::<mdpage::LinkType>()?;
 
+    Ok(mdpage::Link {
+        link_type: Some(link_type),
+        src: Some(String::from(src)),
+        integrity: None,
+        crossorigin: None,
+    })
+}
+
+fn non_empty<T: Clone>(values: &[T]) -> Option<Vec<T>> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.to_vec())
+    }
+}
+
+// The flags that are not set are left to the config file and the environment.
+fn flag(set: bool) -> Option<bool> {
+    if set {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
     let opt = Args::from_args();
 
-    let root = opt.path.as_path();
+    let filter = if opt.watch || matches!(opt.cmd, Some(Command::Serve { .. })) {
+        "info"
+    } else {
+        "warn"
+    };
+    env_logger::from_env(Env::default().default_filter_or(filter)).init();
+
+    match opt.cmd {
+        Some(Command::Serve {
+            build,
+            host,
+            port,
+            path,
+        }) => {
+            let initial = build.initial()?;
+            let address = format!("{}:{}", host, port);
+            let config = mdpage::resolve_config(&path, Some(initial.clone()))?.file;
+            mdpage::serve(&path, &address, config.as_deref(), || {
+                mdpage::build(&path, Some(initial.clone()))
+            })?;
+            return Ok(());
+        }
+        Some(Command::Check { build, path }) => {
+            let problems = mdpage::check(&path, Some(build.initial()?))?;
+            for problem in problems.iter() {
+                println!("{}", problem);
+            }
+
+            if problems.is_empty() {
+                println!("no problems found");
+                return Ok(());
+            }
+
+            println!("{} problem(s) found", problems.len());
+            std::process::exit(1);
+        }
+        Some(Command::Config { build, path }) => {
+            let config = mdpage::resolve_config(&path, Some(build.initial()?))?;
+            if let Some(file) = config.file.as_ref() {
+                println!("# config file: {}", file.display());
+            }
+            print!("{}", config);
+            return Ok(());
+        }
+        None => {}
+    }
 
-    let initial = mdpage::Data {
-        title: opt.title,
-        subtitle: opt.subtitle,
-        full_page: Some(opt.full_page),
-        ..mdpage::Data::default()
+    let dir = match opt.paths.as_slice() {
+        [path] if path.is_dir() => Some(path.clone()),
+        _ => None,
     };
 
-    let data = mdpage::build(root, Some(initial))?;
-    debug!("{}", serde_json::to_string(&data).expect("failed to json"));
+    // the markdown files are rendered without discovering the contents of their directory
+    let files = match dir {
+        Some(_) => Vec::new(),
+        None => opt
+            .paths
+            .iter()
+            .map(|path| input_file(path))
+            .collect::<mdpage::Result<Vec<mdpage::Content>>>()?,
+    };
+    let root = dir.clone().unwrap_or_else(|| files_dir(&opt.paths));
+
+    let initial = opt.build.initial()?;
+    let build = || match dir.as_ref() {
+        Some(dir) => mdpage::build(dir, Some(initial.clone())),
+        None => mdpage::build_files(files.clone(), Some(initial.clone())),
+    };
+
+    let data = build()?;
 
     let path = match opt.output {
         Some(o) => o,
-        None => {
-            let mut path = root.to_path_buf();
-            path.push("index.html");
-            path
-        }
+        None if data.multi_page == Some(true) => root.join("site"),
+        None => root.join("index.html"),
     };
 
-    let f = File::create(&path).map_err(|err| {
-        format!(
-            "could not create path: {}. {}",
-            path.display(),
-            err.to_string()
-        )
+    write_output(&path, &data)?;
+
+    if opt.watch {
+        if is_stdio(&path) {
+            return Err("the standard output can not be used in watch mode".into());
+        }
+
+        if data.multi_page == Some(true) && root.canonicalize()?.starts_with(path.canonicalize()?) {
+            return Err(format!(
+                "output directory must not contain the root directory in watch mode: {}",
+                path.display()
+            )
+            .into());
+        }
+
+        // the config file of the markdown files is only the one that is set explicitly
+        let config = match dir.as_ref() {
+            Some(dir) => mdpage::resolve_config(dir, Some(initial.clone()))?.file,
+            None => initial.config.clone(),
+        };
+
+        mdpage::watch(
+            &root,
+            &data,
+            config.as_deref(),
+            std::slice::from_ref(&path),
+            || {
+                let data = build()?;
+                write_output(&path, &data)?;
+                Ok(data)
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+fn is_stdio(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+// The markdown file to render, or the markdown read from the standard input.
+fn input_file(path: &Path) -> mdpage::Result<mdpage::Content> {
+    if !is_stdio(path) {
+        return Ok(mdpage::Content {
+            file: Some(path.to_path_buf()),
+            ..mdpage::Content::default()
+        });
+    }
+
+    let mut markdown = String::new();
+    std::io::stdin()
+        .read_to_string(&mut markdown)
+        .map_err(|source| mdpage::Error::ReadFile {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+    Ok(mdpage::Content {
+        markdown: Some(markdown),
+        ..mdpage::Content::default()
+    })
+}
+
+// The directory of the first markdown file, where the output is written by default.
+fn files_dir(paths: &[PathBuf]) -> PathBuf {
+    paths
+        .iter()
+        .filter(|path| !is_stdio(path))
+        .find_map(|path| path.parent())
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+fn write_output(path: &Path, data: &mdpage::Data) -> mdpage::Result<()> {
+    debug!("{}", serde_json::to_string(&data).expect("failed to json"));
+
+    if data.multi_page == Some(true) {
+        if is_stdio(path) {
+            return Err(mdpage::Error::Output {
+                source: std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "multi page documentation can not be written to the standard output",
+                ),
+            });
+        }
+        return mdpage::write_pages(path, data);
+    }
+
+    if is_stdio(path) {
+        return mdpage::write_data(std::io::stdout().lock(), data);
+    }
+
+    let f = File::create(path).map_err(|source| mdpage::Error::WriteFile {
+        path: path.to_path_buf(),
+        source,
     })?;
 
-    mdpage::write_data(f, &data)
+    mdpage::write_data(f, data)
 }
@@ -0,0 +1,129 @@
+//! Options of the markdown rendering set in the configuration.
+
+use comrak::nodes::{AstNode, NodeValue};
+use comrak::ComrakOptions;
+use serde::{Deserialize, Serialize};
+
+const RAW_HTML_OMITTED: &[u8] = b"<!-- raw HTML omitted -->";
+
+/// The markdown extensions and rendering options.
+/// The options that are not set use the defaults, which enable the GitHub flavored markdown extensions
+/// along with the raw HTML in the markdown.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct MarkdownOptions {
+    /// Whether to render `~~text~~` as strikethrough, enabled by default.
+    pub strikethrough: Option<bool>,
+    /// Whether to turn URLs and email addresses into links, enabled by default.
+    pub autolink: Option<bool>,
+    /// Whether to render tables, enabled by default.
+    pub table: Option<bool>,
+    /// Whether to render `- [ ]` list items as task lists, enabled by default.
+    pub tasklist: Option<bool>,
+    /// Whether to escape the raw HTML tags that are not allowed by GitHub, such as `<script>`, enabled by default.
+    pub tagfilter: Option<bool>,
+    /// Whether to render footnotes.
+    pub footnotes: Option<bool>,
+    /// Whether to render description lists.
+    pub description_lists: Option<bool>,
+    /// Whether to render `^text^` as superscript.
+    pub superscript: Option<bool>,
+    /// Whether to add anchor IDs to the headings, prefixed with the content label.
+    /// The IDs are always added when the table of contents is enabled.
+    pub header_ids: Option<bool>,
+    /// Whether to convert quotes, dashes and ellipses into their typographic versions.
+    pub smart: Option<bool>,
+    /// Whether to render the line breaks within paragraphs as hard line breaks.
+    pub hardbreaks: Option<bool>,
+    /// Whether to render the raw HTML in the markdown, enabled by default.
+    /// When disabled the raw HTML is left out of the document.
+    #[serde(rename = "unsafe")]
+    pub unsafe_html: Option<bool>,
+}
+
+impl MarkdownOptions {
+    /// Generate the comrak options from the set options and the defaults.
+    /// The header IDs are not set as the prefix depends on the content.
+    pub fn comrak_options(&self) -> ComrakOptions {
+        ComrakOptions {
+            ext_strikethrough: self.strikethrough.unwrap_or(true),
+            ext_autolink: self.autolink.unwrap_or(true),
+            ext_table: self.table.unwrap_or(true),
+            ext_tasklist: self.tasklist.unwrap_or(true),
+            ext_tagfilter: self.tagfilter.unwrap_or(true),
+            ext_footnotes: self.footnotes.unwrap_or(false),
+            ext_description_lists: self.description_lists.unwrap_or(false),
+            ext_superscript: self.superscript.unwrap_or(false),
+            smart: self.smart.unwrap_or(false),
+            hardbreaks: self.hardbreaks.unwrap_or(false),
+            unsafe_: self.unsafe_html.unwrap_or(true),
+            ..ComrakOptions::default()
+        }
+    }
+}
+
+/// Replace the raw HTML in the document with a comment, the same way as when rendering with raw HTML disabled.
+/// This is used to render the document with raw HTML enabled for the generated HTML, such as the highlighted code,
+/// while still leaving out the raw HTML in the markdown.
+pub fn omit_raw_html<'a>(root: &'a AstNode<'a>) {
+    for node in root.descendants() {
+        match node.data.borrow_mut().value {
+            NodeValue::HtmlBlock(ref mut block) => block.literal = RAW_HTML_OMITTED.to_vec(),
+            NodeValue::HtmlInline(ref mut literal) => *literal = RAW_HTML_OMITTED.to_vec(),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use comrak::{format_html, markdown_to_html, parse_document, Arena};
+
+    #[test]
+    fn test_comrak_options() {
+        let options = MarkdownOptions::default().comrak_options();
+        assert!(options.ext_table && options.ext_tasklist && options.unsafe_);
+        assert!(!options.ext_footnotes && !options.smart);
+        assert_eq!(
+            markdown_to_html("~~old~~ <b>new</b>", &options),
+            "<p><del>old</del> <b>new</b></p>\n"
+        );
+
+        let options = MarkdownOptions {
+            strikethrough: Some(false),
+            superscript: Some(true),
+            smart: Some(true),
+            unsafe_html: Some(false),
+            ..MarkdownOptions::default()
+        }
+        .comrak_options();
+        assert_eq!(
+            markdown_to_html("~~old~~ e=mc^2^ \"new\" <b>raw</b>", &options),
+            "<p>~~old~~ e=mc<sup>2</sup> “new” <!-- raw HTML omitted -->raw<!-- raw HTML omitted --></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_omit_raw_html() {
+        let markdown = "<div>\nblock\n</div>\n\nSome <b>inline</b> HTML.\n";
+        let options = MarkdownOptions {
+            unsafe_html: Some(false),
+            ..MarkdownOptions::default()
+        }
+        .comrak_options();
+
+        let arena = Arena::new();
+        let root = parse_document(&arena, markdown, &options);
+        omit_raw_html(root);
+
+        let mut html = Vec::new();
+        let unsafe_options = MarkdownOptions::default().comrak_options();
+        format_html(root, &unsafe_options, &mut html).unwrap();
+        assert_eq!(
+            String::from_utf8(html).unwrap(),
+            markdown_to_html(markdown, &options)
+        );
+    }
+}
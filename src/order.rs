@@ -0,0 +1,319 @@
+//! Ordering of the contents discovered in the directories.
+
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::content::Content;
+use crate::error::{Error, Result};
+
+/// The name of the file listing the order of the entries in a directory.
+pub const ORDER_FILE: &str = "_order";
+
+/// The strategy used to order the discovered contents in the menu.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    /// Order by the file name.
+    #[default]
+    File,
+    /// Order by the file name, comparing the numbers within the names by their value.
+    Natural,
+    /// Order like `natural`, and remove the numeric prefix such as `01-` from the labels
+    /// inferred from the file and directory names.
+    Numeric,
+    /// Order by the label.
+    Title,
+}
+
+impl FromStr for SortOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<SortOrder, String> {
+        match s {
+            "file" => Ok(SortOrder::File),
+            "natural" => Ok(SortOrder::Natural),
+            "numeric" => Ok(SortOrder::Numeric),
+            "title" => Ok(SortOrder::Title),
+            _ => Err(format!(
+                "unknown sort order: {}. Expected file, natural, numeric or title",
+                s
+            )),
+        }
+    }
+}
+
+impl SortOrder {
+    /// Compare two entries by their file names and labels.
+    fn compare(self, a: (&str, Option<&str>), b: (&str, Option<&str>)) -> Ordering {
+        match self {
+            SortOrder::File => a.0.cmp(b.0),
+            SortOrder::Natural | SortOrder::Numeric => natural_cmp(a.0, b.0),
+            SortOrder::Title => {
+                let a_label = a.1.unwrap_or_default().to_lowercase();
+                let b_label = b.1.unwrap_or_default().to_lowercase();
+                a_label.cmp(&b_label).then_with(|| natural_cmp(a.0, b.0))
+            }
+        }
+    }
+}
+
+/// Compare the strings comparing the runs of digits by their numeric value,
+/// so that `2-setup` comes before `10-faq`. Letters are compared ignoring the case.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        let (x, y) = match (a_chars.peek(), b_chars.peek()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(&x), Some(&y)) => (x, y),
+        };
+
+        let ord = if x.is_ascii_digit() && y.is_ascii_digit() {
+            let x_num = take_number(&mut a_chars);
+            let y_num = take_number(&mut b_chars);
+            let x_num = x_num.trim_start_matches('0');
+            let y_num = y_num.trim_start_matches('0');
+            x_num.len().cmp(&y_num.len()).then_with(|| x_num.cmp(y_num))
+        } else {
+            a_chars.next();
+            b_chars.next();
+            x.to_lowercase().cmp(y.to_lowercase())
+        };
+
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+}
+
+fn take_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut res = String::new();
+    while let Some(c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+        res.push(*c);
+        chars.next();
+    }
+    res
+}
+
+/// Remove the numeric prefix such as `01-`, `2_` or `3.` from the name.
+pub fn strip_numeric_prefix(name: &str) -> &str {
+    let rest = name.trim_start_matches(|c: char| c.is_ascii_digit());
+    if rest.len() == name.len() {
+        return name;
+    }
+
+    match rest.chars().next() {
+        Some(sep @ '-') | Some(sep @ '_') | Some(sep @ '.') | Some(sep @ ' ') => {
+            let stripped = rest[sep.len_utf8()..].trim_start();
+            if stripped.is_empty() {
+                name
+            } else {
+                stripped
+            }
+        }
+        _ => name,
+    }
+}
+
+/// Read the order file of the directory, listing the names of the files and subdirectories one per line.
+/// Empty lines and lines starting with `#` are ignored. Returns an empty list if there is no order file.
+pub fn read_order_file(dir: &Path) -> Result<Vec<String>> {
+    let path = dir.join(ORDER_FILE);
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&path).map_err(|err| Error::read_file(&path, err))?;
+
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+/// The position of the path in the order list, matching either the file name or the name without
+/// the extension. The paths not listed come last.
+fn position(order: &[String], path: Option<&Path>) -> usize {
+    let name = path.and_then(|p| p.file_name()).and_then(|n| n.to_str());
+    let stem = path.and_then(|p| p.file_stem()).and_then(|n| n.to_str());
+
+    order
+        .iter()
+        .position(|o| Some(o.as_str()) == name || Some(o.as_str()) == stem)
+        .unwrap_or(order.len())
+}
+
+fn file_name(path: Option<&Path>) -> &str {
+    path.and_then(|p| p.file_name())
+        .and_then(|n| n.to_str())
+        .unwrap_or_default()
+}
+
+/// Order the contents of the directory. The contents listed in the order file come first,
+/// followed by the contents with a weight, and then the rest ordered using the strategy.
+/// With the numeric strategy the prefix is also removed from the labels inferred from the file names.
+pub fn order_contents(contents: &mut [Content], dir: &Path, sort: SortOrder) -> Result<()> {
+    if sort == SortOrder::Numeric {
+        for c in contents.iter_mut() {
+            let stem = c
+                .file
+                .as_deref()
+                .and_then(|p| p.file_stem())
+                .and_then(|n| n.to_str());
+            if let (Some(stem), Some(label)) = (stem, c.label.as_mut()) {
+                if label == stem {
+                    *label = String::from(strip_numeric_prefix(stem));
+                }
+            }
+        }
+    }
+
+    let order = read_order_file(dir)?;
+
+    contents.sort_by(|a, b| {
+        let (a_file, b_file) = (a.file.as_deref(), b.file.as_deref());
+
+        position(&order, a_file)
+            .cmp(&position(&order, b_file))
+            .then_with(|| (a.weight.is_none(), a.weight).cmp(&(b.weight.is_none(), b.weight)))
+            .then_with(|| {
+                sort.compare(
+                    (file_name(a_file), a.label.as_deref()),
+                    (file_name(b_file), b.label.as_deref()),
+                )
+            })
+    });
+
+    Ok(())
+}
+
+/// Order the groups of contents of the subdirectories of the directory, by the order file and then
+/// using the strategy. The label of each group is the label of its heading.
+pub fn order_sections(
+    sections: &mut [(PathBuf, Vec<Content>)],
+    dir: &Path,
+    sort: SortOrder,
+) -> Result<()> {
+    let order = read_order_file(dir)?;
+    let label = |contents: &[Content]| contents.first().and_then(|c| c.label.clone());
+
+    sections.sort_by(|(a_dir, a), (b_dir, b)| {
+        let (a_label, b_label) = (label(a), label(b));
+
+        position(&order, Some(a_dir))
+            .cmp(&position(&order, Some(b_dir)))
+            .then_with(|| {
+                sort.compare(
+                    (file_name(Some(a_dir)), a_label.as_deref()),
+                    (file_name(Some(b_dir)), b_label.as_deref()),
+                )
+            })
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_natural_cmp() {
+        let mut names = vec![
+            "10-faq",
+            "2-setup",
+            "01-intro",
+            "Appendix",
+            "api",
+            "2-setup-b",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            vec![
+                "01-intro",
+                "2-setup",
+                "2-setup-b",
+                "10-faq",
+                "api",
+                "Appendix"
+            ]
+        );
+
+        assert_eq!(natural_cmp("file2", "file10"), Ordering::Less);
+        assert_eq!(natural_cmp("v1.10", "v1.9"), Ordering::Greater);
+        assert_eq!(natural_cmp("a01", "a1"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_strip_numeric_prefix() {
+        assert_eq!(strip_numeric_prefix("01-intro"), "intro");
+        assert_eq!(strip_numeric_prefix("2_setup"), "setup");
+        assert_eq!(strip_numeric_prefix("3. Usage"), "Usage");
+        assert_eq!(strip_numeric_prefix("2020"), "2020");
+        assert_eq!(strip_numeric_prefix("3d-models"), "3d-models");
+        assert_eq!(strip_numeric_prefix("intro"), "intro");
+        assert_eq!(strip_numeric_prefix("10-"), "10-");
+    }
+
+    #[test]
+    fn test_sort_order_from_str() {
+        assert_eq!("numeric".parse::<SortOrder>(), Ok(SortOrder::Numeric));
+        assert_eq!("title".parse::<SortOrder>(), Ok(SortOrder::Title));
+        assert!("size".parse::<SortOrder>().is_err());
+    }
+
+    #[test]
+    fn test_order_contents() {
+        let content = |file: &str, label: &str, weight: Option<i64>| Content {
+            file: Some(PathBuf::from(file)),
+            label: Some(String::from(label)),
+            weight,
+            ..Content::default()
+        };
+        let labels = |contents: &[Content]| {
+            contents
+                .iter()
+                .map(|c| c.label.clone().unwrap())
+                .collect::<Vec<String>>()
+        };
+
+        let mut contents = vec![
+            content("docs/10-faq.md", "10-faq", None),
+            content("docs/2-setup.md", "Setup", None),
+            content("docs/01-intro.md", "01-intro", None),
+            content("docs/zeta.md", "Alpha", Some(1)),
+        ];
+
+        let dir = Path::new("tests/fixtures/unknown_dir");
+        order_contents(&mut contents, dir, SortOrder::File).unwrap();
+        assert_eq!(
+            labels(&contents),
+            vec!["Alpha", "01-intro", "10-faq", "Setup"]
+        );
+
+        order_contents(&mut contents, dir, SortOrder::Natural).unwrap();
+        assert_eq!(
+            labels(&contents),
+            vec!["Alpha", "01-intro", "Setup", "10-faq"]
+        );
+
+        order_contents(&mut contents, dir, SortOrder::Title).unwrap();
+        assert_eq!(
+            labels(&contents),
+            vec!["Alpha", "01-intro", "10-faq", "Setup"]
+        );
+
+        order_contents(&mut contents, dir, SortOrder::Numeric).unwrap();
+        assert_eq!(labels(&contents), vec!["Alpha", "intro", "Setup", "faq"]);
+    }
+}
@@ -0,0 +1,127 @@
+//! Sanitization of the rendered HTML in safe mode.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+use ammonia::Builder;
+
+/// The prefixes of the IDs of the page elements, which the content can not use.
+const RESERVED_ID_PREFIXES: &[&str] = &["doc-section-", "menu-", "search-"];
+
+/// Sanitize the HTML by removing scripts, event handlers, `javascript:` URLs
+/// and any other elements and attributes that are not known to be safe.
+/// The markup generated for the markdown, such as the heading anchors, task lists, footnotes
+/// and the highlighted code, is kept. IDs are only kept on the headings, their anchors and the footnotes,
+/// and classes only on the generated elements.
+pub fn sanitize_html(html: &str) -> String {
+    let mut classes = HashMap::new();
+    classes.insert("a", set(&["anchor", "footnote-backref"]));
+    classes.insert("sup", set(&["footnote-ref"]));
+    classes.insert("section", set(&["footnotes"]));
+    classes.insert("pre", set(&["hl-code"]));
+
+    Builder::default()
+        .add_tags(&["section", "input"])
+        .add_tag_attributes("a", &["aria-hidden", "id"])
+        .add_tag_attributes("li", &["id"])
+        .add_tag_attributes("h1", &["id"])
+        .add_tag_attributes("h2", &["id"])
+        .add_tag_attributes("h3", &["id"])
+        .add_tag_attributes("h4", &["id"])
+        .add_tag_attributes("h5", &["id"])
+        .add_tag_attributes("h6", &["id"])
+        .add_tag_attributes("code", &["class"])
+        .add_tag_attributes("span", &["class"])
+        .add_tag_attributes("input", &["type", "checked", "disabled"])
+        .add_tag_attributes("th", &["align"])
+        .add_tag_attributes("td", &["align"])
+        .allowed_classes(classes)
+        .attribute_filter(filter_attribute)
+        .clean(html)
+        .to_string()
+}
+
+fn set(classes: &[&'static str]) -> HashSet<&'static str> {
+    classes.iter().copied().collect()
+}
+
+/// Remove the IDs of the page elements, and keep only the language class of the code
+/// and the highlighting classes of the spans.
+fn filter_attribute<'u>(element: &str, attribute: &str, value: &'u str) -> Option<Cow<'u, str>> {
+    match (element, attribute) {
+        (_, "id") if RESERVED_ID_PREFIXES.iter().any(|p| value.starts_with(p)) => None,
+        ("code", "class") => filter_classes(value, "language-"),
+        ("span", "class") => filter_classes(value, "hl-"),
+        _ => Some(value.into()),
+    }
+}
+
+fn filter_classes<'u>(value: &'u str, prefix: &str) -> Option<Cow<'u, str>> {
+    let classes = value
+        .split_ascii_whitespace()
+        .filter(|c| c.starts_with(prefix))
+        .collect::<Vec<&str>>();
+
+    if classes.is_empty() {
+        None
+    } else {
+        Some(classes.join(" ").into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_html() {
+        assert_eq!(
+            sanitize_html(
+                r#"<p onclick="steal()">Hi<script>alert(1)</script> <a href="javascript:alert(1)">link</a></p>"#
+            ),
+            r#"<p>Hi <a rel="noopener noreferrer">link</a></p>"#
+        );
+        assert_eq!(
+            sanitize_html(
+                r#"<iframe src="https://example.com"></iframe><img src="a.png" onerror="x()">"#
+            ),
+            r#"<img src="a.png">"#
+        );
+        assert_eq!(
+            sanitize_html(r#"<span style="position:fixed;top:0">overlay</span>"#),
+            "<span>overlay</span>"
+        );
+
+        // the IDs and classes of the page elements can not be used by the content
+        assert_eq!(
+            sanitize_html(
+                r#"<div id="doc-section-0" class="is-hidden">x</div><h2 id="menu-1">y</h2><a id="doc-section-main" class="menu">z</a>"#
+            ),
+            r#"<div>x</div><h2>y</h2><a rel="noopener noreferrer" class="">z</a>"#
+        );
+        assert_eq!(
+            sanitize_html(
+                r#"<pre class="menu hl-code"><code class="is-hidden language-rust"><span class="hl-keyword menu">fn</span></code></pre><p class="hl-code">p</p>"#
+            ),
+            r#"<pre class="hl-code"><code class="language-rust"><span class="hl-keyword">fn</span></code></pre><p>p</p>"#
+        );
+
+        // generated markup is kept
+        let html = r##"<h2><a href="#usage" aria-hidden="true" class="anchor" id="api-usage"></a>Usage</h2>
+<ul>
+<li><input type="checkbox" disabled="" checked="" /> Done</li>
+</ul>
+<pre class="hl-code"><code class="language-rust"><span class="hl-storage hl-type">fn</span></code></pre>
+<p>Note<sup class="footnote-ref"><a href="#fn1" id="fnref1">1</a></sup></p>
+<section class="footnotes">
+<ol>
+<li id="fn1"><p>Text <a href="#fnref1" class="footnote-backref">↩</a></p></li>
+</ol>
+</section>
+"##;
+        assert_eq!(
+            sanitize_html(html).replace(r#" rel="noopener noreferrer""#, ""),
+            html.replace(r#"disabled="" checked="" /"#, r#"disabled="" checked="""#)
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! Search index of the document contents used for the client-side search.
+
+use comrak::nodes::NodeValue;
+use comrak::{parse_document, Arena, ComrakOptions};
+use serde::Serialize;
+
+use crate::content::Content;
+use crate::data::Data;
+use crate::toc::collect_text;
+
+/// A single searchable part of the document, namely a section or a heading within a section.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SearchEntry {
+    /// The index of the content in the contents, or `None` for the main content.
+    pub section: Option<usize>,
+    /// The label of the content.
+    pub label: String,
+    /// The heading within the content, if the entry is not the beginning of the content.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heading: Option<String>,
+    /// The plain text of the section or heading.
+    pub text: String,
+    /// The page file of the section, used in multi page mode.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+/// Build the search index from the main content and all the contents.
+pub fn build_index(data: &Data) -> Vec<SearchEntry> {
+    let mut res = Vec::new();
+
+    if let Some(main) = data.main.as_ref() {
+        let label = data.title.clone().unwrap_or_default();
+        index_content(main, None, label, &mut res);
+    }
+
+    if let Some(contents) = data.contents.as_ref() {
+        for (i, c) in contents.iter().enumerate() {
+            if c.is_heading == Some(true) || c.is_break == Some(true) || c.url.is_some() {
+                continue;
+            }
+
+            let label = c.label.clone().unwrap_or_default();
+            index_content(c, Some(i), label, &mut res);
+        }
+    }
+
+    res
+}
+
+fn index_content(c: &Content, section: Option<usize>, label: String, res: &mut Vec<SearchEntry>) {
+    let parts = if let Some(markdown) = c.markdown.as_ref() {
+        markdown_parts(markdown)
+    } else if let Some(html) = c.html.as_ref() {
+        vec![(None, html_text(html))]
+    } else {
+        return;
+    };
+
+    for (heading, text) in parts {
+        if heading.is_none() && text.is_empty() {
+            continue;
+        }
+
+        res.push(SearchEntry {
+            section,
+            label: label.clone(),
+            heading,
+            text,
+            file: None,
+        });
+    }
+}
+
+/// Split the markdown into the text before the first heading and the text under each heading.
+fn markdown_parts(markdown: &str) -> Vec<(Option<String>, String)> {
+    let arena = Arena::new();
+    let options = ComrakOptions {
+        ext_strikethrough: true,
+        ext_table: true,
+        ext_tasklist: true,
+        ..ComrakOptions::default()
+    };
+    let root = parse_document(&arena, markdown, &options);
+
+    let mut parts: Vec<(Option<String>, String)> = vec![(None, String::new())];
+
+    for node in root.children() {
+        if let NodeValue::Heading(_) = node.data.borrow().value {
+            let mut heading = String::new();
+            collect_text(node, &mut heading);
+            parts.push((Some(heading.trim().to_owned()), String::new()));
+            continue;
+        }
+
+        if let Some((_, text)) = parts.last_mut() {
+            collect_text(node, text);
+            text.push(' ');
+        }
+    }
+
+    parts
+        .into_iter()
+        .map(|(heading, text)| (heading, normalize_whitespace(&text)))
+        .collect()
+}
+
+/// Extract the text from HTML content by removing all the tags.
+fn html_text(html: &str) -> String {
+    let mut res = String::with_capacity(html.len());
+    let mut in_tag = false;
+
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                res.push(' ');
+            }
+            _ if !in_tag => res.push(c),
+            _ => {}
+        }
+    }
+
+    normalize_whitespace(&res)
+}
+
+fn normalize_whitespace(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_parts() {
+        let parts = markdown_parts(
+            "Intro *text*.\n\n# Title\n\nSome `code` here.\n\n## Sub **title**\n\n- one\n- two\n",
+        );
+        assert_eq!(
+            parts,
+            vec![
+                (None, String::from("Intro text.")),
+                (Some(String::from("Title")), String::from("Some code here.")),
+                (Some(String::from("Sub title")), String::from("one two")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_html_text() {
+        assert_eq!(
+            html_text("<h1>Some title</h1>\n<p>Some <b>bold</b> text.</p>"),
+            "Some title Some bold text."
+        );
+    }
+
+    #[test]
+    fn test_build_index() {
+        let data = Data {
+            title: Some(String::from("Doc")),
+            main: Some(Content {
+                markdown: Some(String::from("# Doc\n\nMain content.")),
+                ..Content::default()
+            }),
+            contents: Some(vec![
+                Content::new_heading(String::from("Guide")),
+                Content {
+                    label: Some(String::from("Install")),
+                    markdown: Some(String::from("Run the installer.")),
+                    ..Content::default()
+                },
+                Content {
+                    label: Some(String::from("Page")),
+                    html: Some(String::from("<p>HTML page</p>")),
+                    ..Content::default()
+                },
+            ]),
+            ..Data::default()
+        };
+
+        let index = build_index(&data);
+        assert_eq!(index.len(), 3);
+        assert_eq!(index[0].section, None);
+        assert_eq!(index[0].heading, Some(String::from("Doc")));
+        assert_eq!(index[0].text, "Main content.");
+        assert_eq!(index[1].section, Some(1));
+        assert_eq!(index[1].label, "Install");
+        assert_eq!(index[1].heading, None);
+        assert_eq!(index[1].text, "Run the installer.");
+        assert_eq!(index[2].section, Some(2));
+        assert_eq!(index[2].text, "HTML page");
+    }
+}
@@ -0,0 +1,188 @@
+//! Local preview server with live reload.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::thread;
+
+use tiny_http::{Header, Request, Response, Server};
+
+use crate::data::Data;
+use crate::error::{Error, Result};
+use crate::utils::{mime_type, percent_decode};
+use crate::watch::watch;
+use crate::writer::{render_pages, write_data};
+
+/// The path polled by the live reload script for the current version of the page.
+const VERSION_PATH: &str = "/__mdpage/version";
+
+/// The generated pages and their version, which is bumped on every rebuild.
+struct Pages {
+    pages: Vec<(String, String)>,
+    version: u64,
+}
+
+/// Serve the generated document over HTTP on the given address,
+/// with the assets served from the root directory.
+/// `build` is called to build the data initially and every time the sources change,
+/// and open browsers are reloaded after each successful rebuild.
+/// The `config` file is watched along with the sources, see `watch`.
+pub fn serve<F>(root: &Path, address: &str, config: Option<&Path>, mut build: F) -> Result<()>
+where
+    F: FnMut() -> Result<Data>,
+{
+    let root = root.canonicalize().map_err(|source| Error::MissingFile {
+        path: root.to_path_buf(),
+        source,
+    })?;
+
+    let data = build()?;
+    let pages = Arc::new(RwLock::new(Pages {
+        pages: render(&data)?,
+        version: 0,
+    }));
+
+    let server = Server::http(address).map_err(|source| Error::Serve {
+        address: String::from(address),
+        source,
+    })?;
+
+    info!("serving on: http://{}", address);
+
+    let state = Arc::clone(&pages);
+    let dir = root.clone();
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            respond(&dir, &state, request);
+        }
+    });
+
+    watch(&root, &data, config, &[], || {
+        let data = build()?;
+        let rendered = render(&data)?;
+
+        let mut p = pages.write().unwrap_or_else(|err| err.into_inner());
+        p.pages = rendered;
+        p.version += 1;
+
+        Ok(data)
+    })
+}
+
+fn render(data: &Data) -> Result<Vec<(String, String)>> {
+    if data.multi_page == Some(true) {
+        return render_pages(data);
+    }
+
+    let mut buf = Vec::new();
+    write_data(&mut buf, data)?;
+    Ok(vec![(
+        String::from("index.html"),
+        String::from_utf8_lossy(&buf).into_owned(),
+    )])
+}
+
+fn respond(root: &Path, pages: &RwLock<Pages>, request: Request) {
+    let url = request.url().split(['?', '#']).next().unwrap_or_default();
+    let path = percent_decode(url);
+
+    debug!("{} {}", request.method(), path);
+
+    let file = match path.as_str() {
+        "/" => "index.html",
+        p => p.trim_start_matches('/'),
+    };
+
+    let html = pages.read().ok().and_then(|p| {
+        p.pages
+            .iter()
+            .find(|(f, _)| f == file)
+            .map(|(_, html)| inject_reload_script(html, p.version))
+    });
+
+    let response = match html {
+        Some(html) => Some((html.into_bytes(), "text/html; charset=utf-8")),
+        None if path == VERSION_PATH => pages
+            .read()
+            .ok()
+            .map(|p| (p.version.to_string().into_bytes(), "text/plain")),
+        None => resolve_file(root, &path)
+            .and_then(|file| fs::read(&file).ok().map(|bytes| (bytes, mime_type(&file)))),
+    };
+
+    let res = match response {
+        Some((bytes, content_type)) => {
+            let mut r = Response::from_data(bytes);
+            if let Ok(header) = Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()) {
+                r.add_header(header);
+            }
+            if let Ok(header) = Header::from_bytes(&b"Cache-Control"[..], &b"no-store"[..]) {
+                r.add_header(header);
+            }
+            r
+        }
+        None => Response::from_string("Not Found").with_status_code(404),
+    };
+
+    if let Err(err) = request.respond(res) {
+        warn!("could not respond: {}. {}", path, err);
+    }
+}
+
+/// Resolve the request path to a file within the root directory.
+fn resolve_file(root: &Path, path: &str) -> Option<PathBuf> {
+    let file = root
+        .join(path.trim_start_matches('/'))
+        .canonicalize()
+        .ok()?;
+
+    if file.starts_with(root) && file.is_file() {
+        Some(file)
+    } else {
+        None
+    }
+}
+
+/// Insert the live reload script at the end of the document body.
+fn inject_reload_script(html: &str, version: u64) -> String {
+    let script = format!(
+        r#"<script>
+    (function () {{
+        var version = "{}";
+        function check() {{
+            fetch("{}", {{ cache: "no-store" }})
+                .then(function (res) {{ return res.text() }})
+                .then(function (v) {{
+                    if (v !== version) {{
+                        location.reload()
+                    }}
+                }})
+                .catch(function () {{}})
+                .then(function () {{ setTimeout(check, 1000) }})
+        }}
+        setTimeout(check, 1000)
+    }})()
+</script>
+"#,
+        version, VERSION_PATH
+    );
+
+    match html.rfind("</body>") {
+        Some(pos) => format!("{}{}{}", &html[..pos], script, &html[pos..]),
+        None => format!("{}{}", html, script),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inject_reload_script() {
+        let html = inject_reload_script("<html><body><p>Hi</p></body></html>", 3);
+        assert!(html.starts_with("<html><body><p>Hi</p><script>"));
+        assert!(html.ends_with("</script>\n</body></html>"));
+        assert!(html.contains(r#"var version = "3";"#));
+        assert!(html.contains(VERSION_PATH));
+    }
+}
@@ -0,0 +1,251 @@
+//! Splitting of a markdown document into sections at its headings.
+
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use comrak::nodes::NodeValue;
+use comrak::{parse_document, Arena, ComrakOptions};
+use serde::{Deserialize, Serialize};
+
+use crate::content::Content;
+use crate::error::{Error, Result};
+use crate::front_matter::parse_front_matter;
+use crate::toc::collect_text;
+use crate::utils::{get_title_from_file, is_ext, resolve_path};
+
+/// The heading level a markdown content is split at into separate contents.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitLevel {
+    /// Split at the first level headings.
+    H1,
+    /// Split at the second level headings.
+    H2,
+}
+
+impl FromStr for SplitLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<SplitLevel, String> {
+        match s {
+            "h1" => Ok(SplitLevel::H1),
+            "h2" => Ok(SplitLevel::H2),
+            _ => Err(format!("unknown split level: {}. Expected h1 or h2", s)),
+        }
+    }
+}
+
+impl SplitLevel {
+    /// The level of the headings.
+    pub fn level(self) -> u32 {
+        match self {
+            SplitLevel::H1 => 1,
+            SplitLevel::H2 => 2,
+        }
+    }
+}
+
+/// A section of a split markdown document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    /// The text of the heading the section starts with, or none for the part before the first heading.
+    pub title: Option<String>,
+    /// The markdown of the section, including the heading.
+    pub markdown: String,
+}
+
+/// The heading level to split a whole document at. This is the second level if the document has
+/// at most one first level heading, the title, and the first level otherwise.
+pub fn split_level(markdown: &str) -> u32 {
+    if headings(markdown, 1).len() > 1 {
+        1
+    } else {
+        2
+    }
+}
+
+/// Split the markdown at its headings of the level. The part before the first heading is the first section,
+/// without a title, and is left out if it is empty. Only the top level headings are split at,
+/// not the ones within block quotes or lists.
+pub fn split_markdown(markdown: &str, level: u32) -> Vec<Section> {
+    let lines = markdown.lines().collect::<Vec<&str>>();
+
+    let mut res = Vec::new();
+    let mut start = 0;
+    let mut title = None;
+
+    for (line, text) in headings(markdown, level) {
+        // the lines of the headings are 1-based
+        let end = line.saturating_sub(1).clamp(start, lines.len());
+        push_section(&mut res, title, &lines[start..end]);
+        start = end;
+        title = Some(text);
+    }
+    push_section(&mut res, title, &lines[start..]);
+
+    res
+}
+
+/// Split the markdown content at its headings of the level, or the level from `split_level` if not set.
+/// Returns the part before the first heading, which keeps the properties of the content,
+/// and a content for each of the sections labeled with its heading. The sections keep the file, the menu level
+/// and the visibility of the content, so the relative links and assets are still resolved against the file.
+/// Contents that are not markdown are not split.
+pub fn split_content(
+    mut c: Content,
+    root: &Path,
+    level: Option<u32>,
+) -> Result<(Option<Content>, Vec<Content>)> {
+    let markdown = match (c.markdown.as_ref(), c.file.as_ref()) {
+        (Some(markdown), _) => markdown.clone(),
+        (None, Some(file)) if is_ext(file, "md") => {
+            let path = resolve_path(root, file)?;
+            let markdown = fs::read_to_string(&path).map_err(|err| Error::read_file(&path, err))?;
+            let (fm, rest) =
+                parse_front_matter(&markdown).map_err(|source| Error::FrontMatter {
+                    path: path.clone(),
+                    source,
+                })?;
+
+            // the file is not read again when rendering, so its front matter and title are used here
+            if let Some(fm) = fm {
+                fm.fill(&mut c);
+            }
+            if c.label.is_none() {
+                c.label = get_title_from_file(&path, true)?;
+            }
+            rest.trim().to_owned()
+        }
+        _ => return Ok((Some(c), Vec::new())),
+    };
+
+    let level = level.unwrap_or_else(|| split_level(&markdown));
+
+    let mut intro = None;
+    let mut sections = Vec::new();
+    for section in split_markdown(&markdown, level) {
+        match section.title {
+            None => {
+                intro = Some(Content {
+                    markdown: Some(section.markdown),
+                    ..c.clone()
+                })
+            }
+            Some(title) => sections.push(Content {
+                label: Some(title),
+                markdown: Some(section.markdown),
+                file: c.file.clone(),
+                level: c.level,
+                hidden: c.hidden,
+                draft: c.draft,
+                ..Content::default()
+            }),
+        }
+    }
+
+    Ok((intro, sections))
+}
+
+/// Split the contents that have a split level, or all the markdown contents if the default level is set.
+/// The headings, breaks and links are left as they are.
+pub fn split_contents(
+    contents: Vec<Content>,
+    root: &Path,
+    default: Option<SplitLevel>,
+) -> Result<Vec<Content>> {
+    let mut res = Vec::new();
+
+    for c in contents {
+        let level = match c.split.or(default) {
+            Some(level)
+                if c.is_heading != Some(true)
+                    && c.is_break != Some(true)
+                    && c.url.is_none()
+                    && c.html.is_none() =>
+            {
+                level
+            }
+            _ => {
+                res.push(c);
+                continue;
+            }
+        };
+
+        let (intro, mut sections) = split_content(c, root, Some(level.level()))?;
+        res.extend(intro);
+        res.append(&mut sections);
+    }
+
+    Ok(res)
+}
+
+/// The line numbers and the texts of the top level headings of the level.
+fn headings(markdown: &str, level: u32) -> Vec<(usize, String)> {
+    let arena = Arena::new();
+    let root = parse_document(&arena, markdown, &ComrakOptions::default());
+
+    let mut res = Vec::new();
+    for node in root.children() {
+        let ast = node.data.borrow();
+        match ast.value {
+            NodeValue::Heading(ref heading) if heading.level == level => {
+                let mut text = String::new();
+                collect_text(node, &mut text);
+                res.push((ast.start_line as usize, text.trim().to_owned()));
+            }
+            _ => {}
+        }
+    }
+
+    res
+}
+
+fn push_section(sections: &mut Vec<Section>, title: Option<String>, lines: &[&str]) {
+    let markdown = lines.join("\n").trim().to_owned();
+    if title.is_some() || !markdown.is_empty() {
+        sections.push(Section { title, markdown });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_markdown() {
+        let markdown = "# Design\n\nIntro text.\n\n## Goals\n\nSome goals.\n\n### Details\n\n> ## Quoted\n\n## Non *goals*\n\n```\n## not a heading\n```\n";
+
+        assert_eq!(split_level(markdown), 2);
+
+        let sections = split_markdown(markdown, 2);
+        assert_eq!(
+            sections,
+            vec![
+                Section {
+                    title: None,
+                    markdown: String::from("# Design\n\nIntro text."),
+                },
+                Section {
+                    title: Some(String::from("Goals")),
+                    markdown: String::from("## Goals\n\nSome goals.\n\n### Details\n\n> ## Quoted"),
+                },
+                Section {
+                    title: Some(String::from("Non goals")),
+                    markdown: String::from("## Non *goals*\n\n```\n## not a heading\n```"),
+                },
+            ]
+        );
+
+        let markdown = "# One\n\nFirst.\n\nSetext\n======\n\nSecond.\n";
+        assert_eq!(split_level(markdown), 1);
+
+        let sections = split_markdown(markdown, 1);
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].title.as_deref(), Some("One"));
+        assert_eq!(sections[1].title.as_deref(), Some("Setext"));
+        assert_eq!(sections[1].markdown, "Setext\n======\n\nSecond.");
+
+        assert_eq!(split_markdown("", 2), vec![]);
+    }
+}
@@ -0,0 +1,269 @@
+//! Menu structure read from a `SUMMARY.md` file, compatible with mdBook.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use comrak::nodes::{AstNode, NodeValue};
+use comrak::{parse_document, Arena, ComrakOptions};
+use serde::Deserialize;
+
+use crate::content::Content;
+use crate::data::read_config;
+use crate::error::{Error, Result};
+use crate::toc::collect_text;
+use crate::utils::percent_decode;
+
+/// The name of the file listing the contents of the menu.
+pub const SUMMARY_FILE: &str = "SUMMARY.md";
+
+/// The name of the mdBook config file.
+pub const BOOK_FILE: &str = "book.toml";
+
+/// The mdBook config, of which only the book metadata is used.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Book {
+    /// The metadata of the book.
+    pub book: BookMetadata,
+}
+
+/// The metadata of the book in the `[book]` table.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct BookMetadata {
+    /// The title of the book.
+    pub title: Option<String>,
+    /// The authors of the book.
+    pub authors: Option<Vec<String>>,
+    /// The directory with the sources of the book relative to the root, which defaults to `src`.
+    pub src: Option<PathBuf>,
+}
+
+impl Book {
+    /// Read the mdBook config in the root directory, if there is one.
+    pub(crate) fn read(root: &Path) -> Result<Option<Book>> {
+        let path = root.join(BOOK_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+
+        read_config(&path).map(Some)
+    }
+}
+
+/// Find the summary file in the root directory, or in the sources directory of the book if there is none.
+pub(crate) fn summary_file(root: &Path, book: Option<&Book>) -> Option<PathBuf> {
+    let path = root.join(SUMMARY_FILE);
+    if path.is_file() {
+        return Some(path);
+    }
+
+    let src = book?
+        .book
+        .src
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("src"));
+    Some(root.join(src).join(SUMMARY_FILE)).filter(|path| path.is_file())
+}
+
+/// Read the contents listed in the summary file, with the files relative to its directory.
+pub(crate) fn read_summary(path: &Path) -> Result<Vec<Content>> {
+    info!("reading summary: {}", path.display());
+    let markdown = fs::read_to_string(path).map_err(|err| Error::read_file(path, err))?;
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+    Ok(parse_summary(&markdown, dir))
+}
+
+/// Parse the summary into the contents of the menu. The links outside the lists are the prefix and suffix
+/// chapters, the links in the nested lists are the numbered chapters nested in the menu, the headings
+/// other than the title of the summary are the part headings, and the horizontal rules are the separators.
+/// The draft chapters with an empty link are left out.
+pub fn parse_summary(markdown: &str, dir: &Path) -> Vec<Content> {
+    let arena = Arena::new();
+    let root = parse_document(&arena, markdown, &ComrakOptions::default());
+
+    let mut res = Vec::new();
+    for (i, node) in root.children().enumerate() {
+        match node.data.borrow().value {
+            // the first heading is the title of the summary
+            NodeValue::Heading(_) if i == 0 => {}
+            NodeValue::Heading(_) => {
+                let mut text = String::new();
+                collect_text(node, &mut text);
+                res.push(Content::new_heading(text.trim().to_owned()));
+            }
+            NodeValue::ThematicBreak => res.push(Content::new_break()),
+            NodeValue::Paragraph => chapter_links(node, dir, None, &mut res),
+            NodeValue::List(_) => list_chapters(node, dir, 1, &mut res),
+            _ => {}
+        }
+    }
+
+    res
+}
+
+/// Add the chapters of the list items, and the ones of the nested lists one level deeper.
+fn list_chapters<'a>(list: &'a AstNode<'a>, dir: &Path, depth: usize, res: &mut Vec<Content>) {
+    let level = if depth > 1 { Some(depth) } else { None };
+
+    for item in list.children() {
+        for node in item.children() {
+            match node.data.borrow().value {
+                NodeValue::Paragraph => chapter_links(node, dir, level, res),
+                NodeValue::List(_) => list_chapters(node, dir, depth + 1, res),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Add a chapter for each of the links in the paragraph.
+fn chapter_links<'a>(
+    paragraph: &'a AstNode<'a>,
+    dir: &Path,
+    level: Option<usize>,
+    res: &mut Vec<Content>,
+) {
+    for node in paragraph.children() {
+        let url = match node.data.borrow().value {
+            NodeValue::Link(ref link) => String::from_utf8_lossy(&link.url).into_owned(),
+            _ => continue,
+        };
+
+        let mut label = String::new();
+        collect_text(node, &mut label);
+
+        let mut c = Content::new(None);
+        c.label = Some(label.trim().to_owned());
+        c.level = level;
+
+        if url.is_empty() {
+            c.draft = Some(true);
+        } else if url.contains("://") {
+            c.url = Some(url);
+        } else {
+            c.file = Some(dir.join(percent_decode(&url)));
+        }
+
+        res.push(c);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_summary() {
+        let markdown = r#"# Summary
+
+[Introduction](README.md)
+
+# User Guide
+
+- [Installation](guide/installation.md)
+- [Reading Books](guide/reading%20books.md)
+    - [Nested](guide/nested.md)
+        - [Deeper](guide/deeper.md)
+- [Draft]()
+
+---
+
+# Reference
+
+- [Website](https://example.com)
+
+[Contributors](misc/contributors.md)
+"#;
+
+        let contents = parse_summary(markdown, Path::new("src"));
+        let summary = contents
+            .iter()
+            .map(|c| {
+                let kind = if c.is_heading == Some(true) {
+                    "heading"
+                } else if c.is_break == Some(true) {
+                    "break"
+                } else if c.draft == Some(true) {
+                    "draft"
+                } else {
+                    "chapter"
+                };
+                (
+                    kind,
+                    c.label.clone().unwrap_or_default(),
+                    c.level,
+                    c.file.clone().or_else(|| c.url.clone().map(PathBuf::from)),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let file = |f: &str| Some(PathBuf::from(f));
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "chapter",
+                    String::from("Introduction"),
+                    None,
+                    file("src/README.md")
+                ),
+                ("heading", String::from("User Guide"), None, None),
+                (
+                    "chapter",
+                    String::from("Installation"),
+                    None,
+                    file("src/guide/installation.md")
+                ),
+                (
+                    "chapter",
+                    String::from("Reading Books"),
+                    None,
+                    file("src/guide/reading books.md")
+                ),
+                (
+                    "chapter",
+                    String::from("Nested"),
+                    Some(2),
+                    file("src/guide/nested.md")
+                ),
+                (
+                    "chapter",
+                    String::from("Deeper"),
+                    Some(3),
+                    file("src/guide/deeper.md")
+                ),
+                ("draft", String::from("Draft"), None, None),
+                ("break", String::new(), None, None),
+                ("heading", String::from("Reference"), None, None),
+                (
+                    "chapter",
+                    String::from("Website"),
+                    None,
+                    file("https://example.com")
+                ),
+                (
+                    "chapter",
+                    String::from("Contributors"),
+                    None,
+                    file("src/misc/contributors.md")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summary_file() {
+        let root = Path::new("tests/fixtures/book");
+        let book = Book::read(root).unwrap().unwrap();
+        assert_eq!(book.book.title, Some(String::from("The Book")));
+        assert_eq!(
+            summary_file(root, Some(&book)),
+            Some(root.join("src").join(SUMMARY_FILE))
+        );
+        assert_eq!(summary_file(root, None), None);
+
+        assert_eq!(Book::read(Path::new("tests/fixtures/data")).unwrap(), None);
+    }
+}
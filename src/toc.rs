@@ -0,0 +1,136 @@
+//! Table of contents of a content built from the markdown headings.
+
+use comrak::nodes::{AstNode, NodeValue};
+use comrak::{parse_document, Anchorizer, Arena, ComrakOptions};
+use serde::{Deserialize, Serialize};
+
+/// The lowest heading level included in the table of contents.
+const MIN_LEVEL: u32 = 2;
+/// The highest heading level included in the table of contents.
+const MAX_LEVEL: u32 = 3;
+
+/// A single heading in the table of contents.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TocEntry {
+    /// The heading level.
+    pub level: u32,
+    /// The text of the heading.
+    pub title: String,
+    /// The anchor ID of the heading in the rendered HTML.
+    pub id: String,
+}
+
+/// Build the table of contents from the second and third level headings of the markdown.
+/// The IDs match the ones generated by comrak when `ext_header_ids` is set to the same prefix.
+pub fn build_toc(markdown: &str, options: &ComrakOptions, prefix: &str) -> Vec<TocEntry> {
+    headings(markdown, options)
+        .into_iter()
+        .filter(|(level, _, _)| (MIN_LEVEL..=MAX_LEVEL).contains(level))
+        .map(|(level, text, id)| TocEntry {
+            level,
+            title: text.trim().to_owned(),
+            id: format!("{}{}", prefix, id),
+        })
+        .collect()
+}
+
+/// The IDs of all the headings of the markdown, without a prefix.
+pub(crate) fn heading_ids(markdown: &str, options: &ComrakOptions) -> Vec<String> {
+    headings(markdown, options)
+        .into_iter()
+        .map(|(_, _, id)| id)
+        .collect()
+}
+
+/// The level, text and ID of each of the headings of the markdown.
+fn headings(markdown: &str, options: &ComrakOptions) -> Vec<(u32, String, String)> {
+    let arena = Arena::new();
+    let root = parse_document(&arena, markdown, options);
+
+    // all the headings have to be anchorized in order to get the same unique IDs as comrak
+    let mut anchorizer = Anchorizer::new();
+    let mut res = Vec::new();
+
+    for node in root.descendants() {
+        let level = match node.data.borrow().value {
+            NodeValue::Heading(ref heading) => heading.level,
+            _ => continue,
+        };
+
+        let mut text = String::new();
+        collect_text(node, &mut text);
+        let id = anchorizer.anchorize(text.clone());
+        res.push((level, text, id));
+    }
+
+    res
+}
+
+/// Collect all the text within the node, separating the nested blocks with spaces.
+/// For headings this is the same text comrak uses for the anchors.
+pub(crate) fn collect_text<'a>(node: &'a AstNode<'a>, output: &mut String) {
+    match node.data.borrow().value {
+        NodeValue::Text(ref literal) | NodeValue::Code(ref literal) => {
+            output.push_str(&String::from_utf8_lossy(literal))
+        }
+        NodeValue::CodeBlock(ref block) => {
+            output.push_str(&String::from_utf8_lossy(&block.literal))
+        }
+        NodeValue::LineBreak | NodeValue::SoftBreak => output.push(' '),
+        _ => {
+            for n in node.children() {
+                collect_text(n, output);
+                if n.data.borrow().value.block() {
+                    output.push(' ');
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use comrak::markdown_to_html;
+
+    #[test]
+    fn test_build_toc() {
+        let markdown = "# Title\n\n## Getting `started`\n\n### Install\n\n#### Deep\n\n## Install\n\n> ## Quoted";
+        let options = ComrakOptions::default();
+
+        let toc = build_toc(markdown, &options, "guide-");
+        let entries = toc
+            .iter()
+            .map(|e| (e.level, e.title.as_str(), e.id.as_str()))
+            .collect::<Vec<(u32, &str, &str)>>();
+        assert_eq!(
+            entries,
+            vec![
+                (2, "Getting started", "guide-getting-started"),
+                (3, "Install", "guide-install"),
+                (2, "Install", "guide-install-1"),
+                (2, "Quoted", "guide-quoted"),
+            ]
+        );
+
+        // the IDs are the same as the ones in the rendered HTML
+        let options = ComrakOptions {
+            ext_header_ids: Some(String::from("guide-")),
+            ..ComrakOptions::default()
+        };
+        let html = markdown_to_html(markdown, &options);
+        for entry in toc {
+            assert!(html.contains(&format!(r#"id="{}""#, entry.id)));
+        }
+    }
+
+    #[test]
+    fn test_heading_ids() {
+        let markdown = "# Title\n\n## Getting `started`\n\n#### Deep\n\n## Title";
+        assert_eq!(
+            heading_ids(markdown, &ComrakOptions::default()),
+            vec!["title", "getting-started", "deep", "title-1"]
+        );
+    }
+}
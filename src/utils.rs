@@ -1,18 +1,24 @@
-use std::error::Error;
 use std::fs;
-use std::fs::File;
 use std::io::prelude::*;
-use std::io::BufReader;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::front_matter::parse_front_matter;
 
 /// Generate the title from markdown content.
+/// The horizontal rules before the heading are skipped.
 pub fn title_string<R>(mut rdr: R) -> Option<String>
 where
     R: BufRead,
 {
     let mut line = String::new();
 
-    while rdr.read_line(&mut line).expect("Unable to read line") > 0 {
+    while rdr.read_line(&mut line).unwrap_or(0) > 0 {
+        if is_thematic_break(&line) {
+            line.clear();
+            continue;
+        }
+
         let mut trimmed = line.trim();
         if trimmed.starts_with('#') {
             trimmed = trimmed.trim_start_matches('#').trim_matches(' ');
@@ -25,12 +31,31 @@ where
     None
 }
 
+/// Whether the line is a horizontal rule, three or more `-`, `*` or `_` characters optionally separated by spaces.
+fn is_thematic_break(line: &str) -> bool {
+    let mut chars = line.chars().filter(|c| !c.is_whitespace());
+    match chars.next() {
+        Some(first @ ('-' | '*' | '_')) => chars.clone().count() >= 2 && chars.all(|c| c == first),
+        _ => false,
+    }
+}
+
+/// Generate the title from the markdown that remains after its front matter block, see `parse_front_matter`.
+/// A block that is not a front matter mapping is part of the markdown.
+pub fn title_from_markdown(markdown: &str) -> Option<String> {
+    let rest = parse_front_matter(markdown)
+        .map(|(_, rest)| rest)
+        .unwrap_or(markdown);
+
+    title_string(rest.as_bytes())
+}
+
 /// Generate the title from directory.
 pub fn build_title_for_dir(
     root: &Path,
     mut paths: fs::ReadDir,
     check_index: bool,
-) -> Result<String, Box<dyn Error>> {
+) -> Result<String> {
     let mut res = String::new();
 
     if let Some(file_name) = root.file_stem() {
@@ -40,15 +65,10 @@ pub fn build_title_for_dir(
     }
 
     if check_index {
-        let index = paths.find(|p| {
-            if let Ok(entry) = p {
-                return is_index_file(entry);
-            }
-            false
-        });
+        let index = paths.find_map(|p| p.ok().filter(is_index_file));
 
-        if let Some(index_value) = index {
-            let index_path = index_value.unwrap().path();
+        if let Some(index_entry) = index {
+            let index_path = index_entry.path();
 
             if let Some(title) = get_title_from_file(&index_path, false)? {
                 res = title;
@@ -60,10 +80,7 @@ pub fn build_title_for_dir(
 }
 
 /// Generate the title from file path.
-pub fn get_title_from_file(
-    path: &Path,
-    use_file_name: bool,
-) -> Result<Option<String>, Box<dyn Error>> {
+pub fn get_title_from_file(path: &Path, use_file_name: bool) -> Result<Option<String>> {
     let mut res = None;
 
     if use_file_name {
@@ -72,17 +89,9 @@ pub fn get_title_from_file(
         }
     }
 
-    let file = File::open(path).map_err(|err| {
-        format!(
-            "Error opening file: {}. {}",
-            path.display(),
-            err.to_string()
-        )
-    })?;
+    let markdown = fs::read_to_string(path).map_err(|err| Error::read_file(path, err))?;
 
-    let buffer = BufReader::new(file);
-
-    let from_file = title_string(buffer);
+    let from_file = title_from_markdown(&markdown);
     if from_file.is_some() {
         res = from_file;
     }
@@ -90,6 +99,27 @@ pub fn get_title_from_file(
     Ok(res)
 }
 
+/// Read the entries of the directory.
+pub fn read_dir(path: &Path) -> Result<fs::ReadDir> {
+    fs::read_dir(path).map_err(|source| Error::ReadDir {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Resolve the path against the root, if the root is absolute and the path is relative.
+pub fn resolve_path(root: &Path, path: &Path) -> Result<PathBuf> {
+    if root.has_root() && path.is_relative() {
+        let joined = root.join(path);
+        joined.canonicalize().map_err(|source| Error::MissingFile {
+            path: joined,
+            source,
+        })
+    } else {
+        Ok(path.to_path_buf())
+    }
+}
+
 /// Generate whether a dir entry is an index file, namely "index.md" or "readme.md".
 pub fn is_index_file(entry: &std::fs::DirEntry) -> bool {
     if let Ok(file_type) = entry.file_type() {
@@ -117,6 +147,71 @@ pub fn is_ext(path: &Path, ext: &str) -> bool {
         == ext
 }
 
+/// Generate a URL and file name friendly slug from the text.
+pub fn slugify(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+
+    for c in s.trim().chars() {
+        if c.is_alphanumeric() {
+            res.extend(c.to_lowercase());
+        } else if !res.is_empty() && !res.ends_with('-') {
+            res.push('-');
+        }
+    }
+
+    res.trim_end_matches('-').to_owned()
+}
+
+/// Decode the percent-encoded characters in the URL.
+pub fn percent_decode(url: &str) -> String {
+    let bytes = url.as_bytes();
+    let mut res = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+            if let Ok(b) = u8::from_str_radix(hex, 16) {
+                res.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        res.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&res).into_owned()
+}
+
+/// Guess the MIME type of a file from its extension.
+pub fn mime_type(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" => "application/javascript; charset=utf-8",
+        "json" => "application/json",
+        "md" | "txt" => "text/plain; charset=utf-8",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "pdf" => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -150,6 +245,53 @@ mod tests {
             title_string("      ###   Hello world!   ".as_bytes()).unwrap(),
             "Hello world!"
         );
+        assert_eq!(
+            title_string("***\n\n- - -\n# Hello world!".as_bytes()).unwrap(),
+            "Hello world!"
+        );
+        assert_eq!(title_string("--\n# Hello world!".as_bytes()), None);
+    }
+
+    #[test]
+    fn test_title_from_markdown() {
+        assert_eq!(
+            title_from_markdown("---\n# comment\nlabel: Hi\n---\n# Hello world!").unwrap(),
+            "Hello world!"
+        );
+        assert_eq!(
+            title_from_markdown("+++\nlabel = \"Hi\"\n+++\n\n# Hello world!").unwrap(),
+            "Hello world!"
+        );
+
+        // a leading horizontal rule that is never closed is not front matter
+        assert_eq!(
+            title_from_markdown("---\n\n# Intro Title\n").unwrap(),
+            "Intro Title"
+        );
+
+        // a block that is not a mapping is kept as markdown
+        assert_eq!(
+            title_from_markdown("---\n# Between rules\n---\n\n# Hello world!").unwrap(),
+            "Between rules"
+        );
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("/"), "/");
+        assert_eq!(percent_decode("/static/logo.png"), "/static/logo.png");
+        assert_eq!(percent_decode("/my%20image.png"), "/my image.png");
+        assert_eq!(percent_decode("/100%"), "/100%");
+        assert_eq!(percent_decode("/%zz"), "/%zz");
+    }
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Hello world!"), "hello-world");
+        assert_eq!(slugify("  User's Guide  "), "user-s-guide");
+        assert_eq!(slugify("API -- Reference"), "api-reference");
+        assert_eq!(slugify("Größe"), "größe");
+        assert_eq!(slugify("!!!"), "");
     }
 
     #[test]
@@ -172,6 +314,14 @@ mod tests {
 
         assert_eq!(get_title_from_file(&path, true).unwrap().unwrap(), "title2");
 
+        // a leading horizontal rule is not a front matter block
+        path = PathBuf::from("tests/fixtures/utils/rule.md");
+
+        assert_eq!(
+            get_title_from_file(&path, true).unwrap().unwrap(),
+            "Intro Title"
+        );
+
         path = PathBuf::from("tests/fixtures/utils/unknown_file_test.md");
 
         assert!(get_title_from_file(&path, false).is_err());
@@ -241,14 +391,14 @@ mod tests {
         let mut root = PathBuf::from("tests/fixtures");
         if let Ok(entries) = fs::read_dir(root) {
             for entry in entries {
-                assert_eq!(is_index_file(&entry.unwrap()), false);
+                assert!(!is_index_file(&entry.unwrap()));
             }
         }
 
         root = PathBuf::from("tests/fixtures/utils");
         if let Ok(entries) = fs::read_dir(root) {
             for entry in entries {
-                assert_eq!(is_index_file(&entry.unwrap()), false);
+                assert!(!is_index_file(&entry.unwrap()));
             }
         }
 
@@ -260,11 +410,11 @@ mod tests {
         let mut second = is_index_file(&paths.next().unwrap().unwrap()); // consume 2nd
 
         if first {
-            assert_eq!(first, true);
-            assert_eq!(second, false);
+            assert!(first);
+            assert!(!second);
         } else {
-            assert_eq!(first, false);
-            assert_eq!(second, true);
+            assert!(!first);
+            assert!(second);
         }
 
         root = PathBuf::from("tests/fixtures/utils3");
@@ -273,11 +423,11 @@ mod tests {
         second = is_index_file(&paths.next().unwrap().unwrap()); // consume 2nd
 
         if first {
-            assert_eq!(first, true);
-            assert_eq!(second, false);
+            assert!(first);
+            assert!(!second);
         } else {
-            assert_eq!(first, false);
-            assert_eq!(second, true);
+            assert!(!first);
+            assert!(second);
         }
     }
 }
@@ -0,0 +1,268 @@
+//! Watching of the document sources for rebuilding on changes.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::time::Duration;
+
+use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::content::Content;
+use crate::data::Data;
+use crate::error::{Error, Result};
+
+/// The delay used to debounce file system events.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Watch the root directory, the config file and all the sources referenced by the data,
+/// and call `rebuild` whenever anything changes.
+/// The `config` is the config file that was read for the data, if any, see `resolve_config`.
+/// Changes to any of the `exclude` paths, such as the generated output file or directory, are ignored.
+/// Build errors are logged and do not stop the watcher. This function only returns on watcher errors.
+pub fn watch<F>(
+    root: &Path,
+    data: &Data,
+    config: Option<&Path>,
+    exclude: &[PathBuf],
+    mut rebuild: F,
+) -> Result<()>
+where
+    F: FnMut() -> Result<Data>,
+{
+    let root = root.canonicalize().map_err(|source| Error::MissingFile {
+        path: root.to_path_buf(),
+        source,
+    })?;
+    let exclude = exclude
+        .iter()
+        .map(|p| p.canonicalize().unwrap_or_else(|_| p.clone()))
+        .collect::<Vec<PathBuf>>();
+
+    let (tx, rx) = channel();
+    let watch_error = |source| Error::Watch {
+        path: root.clone(),
+        source,
+    };
+    let mut watcher = watcher(tx, DEBOUNCE).map_err(watch_error)?;
+
+    watcher
+        .watch(&root, RecursiveMode::Recursive)
+        .map_err(watch_error)?;
+    let mut extra = source_paths(&root, data, config);
+    watch_extra(&mut watcher, &extra);
+
+    info!("watching for changes: {}", root.display());
+
+    loop {
+        let mut changed = Vec::new();
+        let mut event = rx
+            .recv()
+            .map_err(|err| watch_error(notify::Error::Generic(err.to_string())))?;
+
+        // collect the whole burst of events so we only rebuild once
+        loop {
+            if let Some(p) = changed_path(event, &root, &exclude) {
+                if !changed.contains(&p) {
+                    changed.push(p);
+                }
+            }
+
+            event = match rx.recv_timeout(DEBOUNCE) {
+                Ok(e) => e,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(err) => return Err(watch_error(notify::Error::Generic(err.to_string()))),
+            };
+        }
+
+        if changed.is_empty() {
+            continue;
+        }
+
+        for p in &changed {
+            debug!("changed: {}", p.display());
+        }
+
+        info!(
+            "rebuilding: {} changed",
+            changed
+                .iter()
+                .map(|p| p.strip_prefix(&root).unwrap_or(p).display().to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+
+        match rebuild() {
+            Ok(data) => {
+                info!("rebuild complete");
+
+                let paths = source_paths(&root, &data, config);
+                if paths != extra {
+                    for p in &extra {
+                        let _ = watcher.unwatch(p);
+                    }
+                    watch_extra(&mut watcher, &paths);
+                    extra = paths;
+                }
+            }
+            Err(err) => error!("rebuild failed: {}", err),
+        }
+    }
+}
+
+/// The path changed by the event, or none if the event does not change anything
+/// or the path is one of the excluded paths. A rescan changes the whole root.
+fn changed_path(event: DebouncedEvent, root: &Path, exclude: &[PathBuf]) -> Option<PathBuf> {
+    match event {
+        DebouncedEvent::Create(p)
+        | DebouncedEvent::Write(p)
+        | DebouncedEvent::Remove(p)
+        | DebouncedEvent::Rename(_, p)
+            if !exclude.iter().any(|e| p.starts_with(e)) =>
+        {
+            Some(p)
+        }
+        DebouncedEvent::Rescan => Some(root.to_path_buf()),
+        DebouncedEvent::Error(err, path) => {
+            match path {
+                Some(p) => warn!("watch error: {}. {}", p.display(), err),
+                None => warn!("watch error: {}", err),
+            }
+            None
+        }
+        _ => None,
+    }
+}
+
+fn watch_extra(watcher: &mut RecommendedWatcher, paths: &[PathBuf]) {
+    for p in paths {
+        if let Err(err) = watcher.watch(p, RecursiveMode::NonRecursive) {
+            warn!("could not watch path: {}. {}", p.display(), err);
+        }
+    }
+}
+
+/// Collect the directories of the config file and all the sources that live outside of the root
+/// and would therefore not be covered by watching the root.
+fn source_paths(root: &Path, data: &Data, config: Option<&Path>) -> Vec<PathBuf> {
+    let mut contents: Vec<&Content> = Vec::new();
+    contents.extend(data.main.iter());
+    contents.extend(data.header.iter());
+    contents.extend(data.footer.iter());
+    if let Some(c) = data.contents.as_ref() {
+        contents.extend(c.iter());
+    }
+
+    let mut dirs = contents
+        .into_iter()
+        .filter_map(|c| match (&c.file, &c.dir) {
+            (Some(file), _) => file.parent().map(|p| p.to_path_buf()),
+            (None, Some(dir)) => Some(dir.clone()),
+            _ => None,
+        })
+        .collect::<Vec<PathBuf>>();
+    dirs.extend(data.template_dir.iter().cloned());
+    // the config file is relative to the current directory rather than the root
+    dirs.extend(
+        config
+            .and_then(|p| p.canonicalize().ok())
+            .and_then(|p| p.parent().map(Path::to_path_buf)),
+    );
+
+    let mut res = Vec::new();
+    for d in dirs {
+        let d = root.join(d).canonicalize().unwrap_or_default();
+        if d.is_dir() && !d.starts_with(root) && !res.contains(&d) {
+            res.push(d);
+        }
+    }
+
+    res.sort();
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_path() {
+        let root = Path::new("/docs");
+        let exclude = vec![root.join("index.html"), root.join("site")];
+        let changed = |event| changed_path(event, root, &exclude);
+
+        assert_eq!(
+            changed(DebouncedEvent::Write(root.join("guide.md"))),
+            Some(root.join("guide.md"))
+        );
+        assert_eq!(
+            changed(DebouncedEvent::Rename(
+                root.join("old.md"),
+                root.join("new.md")
+            )),
+            Some(root.join("new.md"))
+        );
+        assert_eq!(changed(DebouncedEvent::Rescan), Some(root.to_path_buf()));
+
+        // the output file and everything within the output directory are excluded
+        assert_eq!(
+            changed(DebouncedEvent::Write(root.join("index.html"))),
+            None
+        );
+        assert_eq!(
+            changed(DebouncedEvent::Create(root.join("site/page.html"))),
+            None
+        );
+        assert_eq!(
+            changed(DebouncedEvent::Write(root.join("index.html.md"))),
+            Some(root.join("index.html.md"))
+        );
+
+        // the notices sent before the debounced events are ignored
+        assert_eq!(
+            changed(DebouncedEvent::NoticeWrite(root.join("guide.md"))),
+            None
+        );
+        assert_eq!(changed(DebouncedEvent::Chmod(root.join("guide.md"))), None);
+    }
+
+    #[test]
+    fn test_source_paths() {
+        let root = Path::new("tests/fixtures").canonicalize().unwrap();
+        let data = Data {
+            main: Some(Content::new(Some(PathBuf::from("data/main.md")))),
+            contents: Some(vec![
+                Content::new(Some(PathBuf::from("../../src/lib.rs"))),
+                Content {
+                    dir: Some(PathBuf::from("../../docs")),
+                    ..Content::default()
+                },
+            ]),
+            template_dir: Some(PathBuf::from("templates")),
+            ..Data::default()
+        };
+
+        let crate_dir = root.parent().unwrap().parent().unwrap();
+        assert_eq!(
+            source_paths(&root, &data, None),
+            vec![crate_dir.join("docs"), crate_dir.join("src")]
+        );
+
+        // the config file chosen outside of the root, and the one within the root which is already watched
+        let config = crate_dir.join("docs/examples/config/mdpage.toml");
+        assert_eq!(
+            source_paths(&root, &data, Some(&config)),
+            vec![
+                crate_dir.join("docs"),
+                crate_dir.join("docs/examples/config"),
+                crate_dir.join("src")
+            ]
+        );
+        assert_eq!(
+            source_paths(
+                &root,
+                &data,
+                Some(Path::new("tests/fixtures/data/dir1/mdpage.json"))
+            ),
+            vec![crate_dir.join("docs"), crate_dir.join("src")]
+        );
+    }
+}
@@ -1,16 +1,29 @@
-use std::error::Error;
+use std::fs;
+use std::path::Path;
 use std::str;
 
+use crate::content::Content;
 use crate::data::Data;
+use crate::error::Error;
+use crate::highlight::{highlight_css, DEFAULT_THEME};
+use crate::search::build_index;
+use crate::utils::{is_ext, read_dir, slugify};
 
 use voca_rs::*;
 
+use serde_json::{json, Value};
+
 use handlebars::{Context, Handlebars, Helper, Output, RenderContext, RenderError};
 
 pub static INDEX: &[u8] = include_bytes!("templates/index.hbs");
 pub static BODY_MULTI: &[u8] = include_bytes!("templates/multi.hbs");
 pub static BODY_FULL: &[u8] = include_bytes!("templates/full.hbs");
+pub static BODY_PAGE: &[u8] = include_bytes!("templates/page.hbs");
 pub static CSS: &[u8] = include_bytes!("templates/css.hbs");
+pub static NORMALIZE: &[u8] = include_bytes!("templates/normalize.hbs");
+pub static SEARCH: &[u8] = include_bytes!("templates/search.hbs");
+pub static TOC: &[u8] = include_bytes!("templates/toc.hbs");
+pub static LINKS: &[u8] = include_bytes!("templates/links.hbs");
 
 handlebars_helper!(capitalize: |s: str| case::capitalize(s, false));
 handlebars_helper!(upper: |s: str| s.to_uppercase());
@@ -34,32 +47,419 @@ pub fn array_length_helper(
     Ok(())
 }
 
-/// Render the data into HTML and write it to the provided writer.
-pub fn write_data(mut writer: impl std::io::Write, data: &Data) -> Result<(), Box<dyn Error>> {
+/// Register all the templates found in the directory using the file name as the template name.
+/// This overrides any of the built-in templates with the same name.
+fn register_template_dir(hb: &mut Handlebars, dir: &Path) -> Result<(), Error> {
+    for entry in read_dir(dir)? {
+        let path = entry
+            .map_err(|source| Error::ReadDir {
+                path: dir.to_path_buf(),
+                source,
+            })?
+            .path();
+
+        if path.is_file() && is_ext(&path, "hbs") {
+            if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+                info!("using template: {}", path.display());
+
+                let template =
+                    fs::read_to_string(&path).map_err(|err| Error::read_file(&path, err))?;
+                hb.register_template_string(name, template)
+                    .map_err(|source| Error::Template {
+                        path: Some(path.clone()),
+                        source: Box::new(source),
+                    })?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Create the template registry with all the helpers and templates registered.
+fn registry(data: &Data) -> Result<Handlebars<'static>, Error> {
     let mut hb = Handlebars::new();
 
     hb.register_helper("capitalize", Box::new(capitalize));
     hb.register_helper("upper", Box::new(upper));
     hb.register_helper("array_length", Box::new(array_length_helper));
 
-    let index = str::from_utf8(INDEX)?;
-    let body_multi = str::from_utf8(BODY_MULTI)?;
-    let body_full = str::from_utf8(BODY_FULL)?;
-    let css = str::from_utf8(CSS)?;
+    let templates = [
+        ("index", INDEX),
+        ("multi", BODY_MULTI),
+        ("full", BODY_FULL),
+        ("page", BODY_PAGE),
+        ("css", CSS),
+        ("normalize", NORMALIZE),
+        ("search", SEARCH),
+        ("toc", TOC),
+        ("links", LINKS),
+    ];
+
+    for (name, template) in templates.iter() {
+        hb.register_template_string(name, String::from_utf8_lossy(template))
+            .map_err(|source| Error::Template {
+                path: None,
+                source: Box::new(source),
+            })?;
+    }
+
+    if let Some(dir) = data.template_dir.as_ref() {
+        register_template_dir(&mut hb, dir)?;
+    }
+
+    Ok(hb)
+}
+
+/// Render the template with the data.
+fn render(hb: &Handlebars, template: &str, value: &Value) -> Result<String, Error> {
+    hb.render(template, value).map_err(|source| Error::Render {
+        template: String::from(template),
+        source: Box::new(source),
+    })
+}
+
+/// Build the template data from the data.
+/// If search is enabled the search index is added as `search_index` JSON,
+/// with each entry linked to its page file if `files` are provided.
+/// If highlighting is enabled the stylesheet of the theme is added as `highlight_css`.
+fn context(data: &Data, files: Option<&[Option<String>]>) -> Result<Value, Error> {
+    let mut value = serde_json::to_value(data).map_err(|err| Error::Render {
+        template: String::from("index"),
+        source: Box::new(RenderError::from(err)),
+    })?;
+
+    if data.search == Some(true) {
+        let mut index = build_index(data);
+
+        if let Some(files) = files {
+            for entry in index.iter_mut() {
+                entry.file = match entry.section {
+                    Some(i) => files.get(i).cloned().flatten(),
+                    None => Some(String::from("index.html")),
+                };
+            }
+        }
 
-    hb.register_template_string("index", index)?;
-    hb.register_template_string("multi", body_multi)?;
-    hb.register_template_string("full", body_full)?;
-    hb.register_template_string("css", css)?;
+        // make sure the JSON can not terminate the script tag it is embedded in
+        let json = serde_json::to_string(&index)
+            .unwrap_or_default()
+            .replace("</", "<\\/");
 
-    let contents = hb.render("index", &data)?;
-    let data = contents.as_bytes();
-    let mut pos = 0;
+        if let Some(obj) = value.as_object_mut() {
+            obj.insert(String::from("search_index"), json!(json));
+        }
+    }
 
-    while pos < data.len() {
-        let bytes_written = writer.write(&data[pos..])?;
-        pos += bytes_written;
+    if data.highlight == Some(true) {
+        let theme = data.highlight_theme.as_deref().unwrap_or(DEFAULT_THEME);
+        if let Some(obj) = value.as_object_mut() {
+            obj.insert(String::from("highlight_css"), json!(highlight_css(theme)));
+        }
     }
 
+    Ok(value)
+}
+
+/// Render the data into HTML and write it to the provided writer.
+pub fn write_data(mut writer: impl std::io::Write, data: &Data) -> Result<(), Error> {
+    let hb = registry(data)?;
+
+    let contents = render(&hb, "index", &context(data, None)?)?;
+    writer
+        .write_all(contents.as_bytes())
+        .map_err(|source| Error::Output { source })?;
+
     Ok(())
 }
+
+/// Determine the page file name for each of the contents.
+/// Only contents with actual HTML content get a page.
+/// If there is no main content the first content page is used as `index.html`.
+pub(crate) fn page_files(contents: &[Content], has_main: bool) -> Vec<Option<String>> {
+    let mut used = vec![String::from("index")];
+
+    let mut files: Vec<Option<String>> = contents
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            if c.is_heading == Some(true)
+                || c.is_break == Some(true)
+                || c.url.is_some()
+                || c.html.is_none()
+            {
+                return None;
+            }
+
+            let mut name = c.label.as_deref().map(slugify).unwrap_or_default();
+            if name.is_empty() {
+                name = format!("page-{}", i);
+            }
+
+            let mut unique = name.clone();
+            let mut n = 1;
+            while used.contains(&unique) {
+                unique = format!("{}-{}", name, n);
+                n += 1;
+            }
+            used.push(unique.clone());
+
+            Some(format!("{}.html", unique))
+        })
+        .collect();
+
+    if !has_main {
+        if let Some(first) = files.iter_mut().find(|f| f.is_some()) {
+            *first = Some(String::from("index.html"));
+        }
+    }
+
+    files
+}
+
+/// Render the data into separate HTML pages, one for the main content and one for each of the contents.
+/// Returns the file name and the rendered HTML of every page, starting with `index.html`.
+/// If there is no main content the first content page is used as `index.html`.
+pub fn render_pages(data: &Data) -> Result<Vec<(String, String)>, Error> {
+    let hb = registry(data)?;
+
+    let empty = Vec::new();
+    let contents = data.contents.as_ref().unwrap_or(&empty);
+    let main_html = data.main.as_ref().and_then(|m| m.html.as_ref());
+    let files = page_files(contents, main_html.is_some());
+
+    // (index, file, label, html) of each page in order
+    let mut pages = Vec::new();
+    if let Some(html) = main_html {
+        let label = data.title.clone().unwrap_or_default();
+        pages.push((None, String::from("index.html"), label, html));
+    }
+    for (i, (c, f)) in contents.iter().zip(files.iter()).enumerate() {
+        if let (Some(f), Some(html)) = (f, c.html.as_ref()) {
+            let label = c.label.clone().unwrap_or_default();
+            pages.push((Some(i), f.clone(), label, html));
+        }
+    }
+
+    let mut value = context(data, Some(&files))?;
+    if let Some(arr) = value.get_mut("contents").and_then(|v| v.as_array_mut()) {
+        for (c, f) in arr.iter_mut().zip(files.iter()) {
+            if let (Some(obj), Some(f)) = (c.as_object_mut(), f) {
+                obj.insert(String::from("page_file"), json!(f));
+            }
+        }
+    }
+
+    let mut res = Vec::new();
+    for (pos, (index, file, _, html)) in pages.iter().enumerate() {
+        let link = |p: Option<&(Option<usize>, String, String, &String)>| {
+            p.map(|(_, file, label, _)| json!({ "file": file, "label": label }))
+        };
+
+        let content = match index {
+            Some(i) => contents.get(*i),
+            None => data.main.as_ref(),
+        };
+
+        let page = json!({
+            "index": index,
+            "id": index.map(|i| i.to_string()).unwrap_or_else(|| String::from("main")),
+            "file": file,
+            "html": html,
+            "description": content.and_then(|c| c.description.as_ref()),
+            "prev": link(pos.checked_sub(1).and_then(|p| pages.get(p))),
+            "next": link(pages.get(pos + 1)),
+        });
+
+        if let Some(obj) = value.as_object_mut() {
+            obj.insert(String::from("page"), page);
+        }
+
+        res.push((file.clone(), render(&hb, "index", &value)?));
+    }
+
+    Ok(res)
+}
+
+/// Render the data into separate HTML pages and write them into the directory.
+/// The directory is created if it does not exist.
+pub fn write_pages(dir: &Path, data: &Data) -> Result<(), Error> {
+    fs::create_dir_all(dir).map_err(|source| Error::WriteFile {
+        path: dir.to_path_buf(),
+        source,
+    })?;
+
+    for (file, html) in render_pages(data)? {
+        let path = dir.join(file);
+        info!("writing page: {}", path.display());
+
+        fs::write(&path, html).map_err(|source| Error::WriteFile {
+            path: path.clone(),
+            source,
+        })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use crate::content::{fill_content, RenderOptions};
+
+    #[test]
+    fn test_write_data_template_dir() {
+        let data = Data {
+            title: Some(String::from("Custom")),
+            template_dir: Some(PathBuf::from("tests/fixtures/templates")),
+            ..Data::default()
+        };
+        let mut buf = Vec::new();
+        assert!(write_data(&mut buf, &data).is_ok());
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "<h1>Custom</h1>\n<p>Extra partial</p>\n\n"
+        );
+
+        let data = Data {
+            template_dir: Some(PathBuf::from("tests/fixtures/unknown_templates")),
+            ..Data::default()
+        };
+        assert!(write_data(Vec::new(), &data).is_err());
+    }
+
+    #[test]
+    fn test_write_data_self_contained() {
+        let mut data = Data {
+            title: Some(String::from("Test")),
+            ..Data::default()
+        };
+        let mut buf = Vec::new();
+        assert!(write_data(&mut buf, &data).is_ok());
+        let html = String::from_utf8(buf).unwrap();
+        assert!(html.contains("normalize.min.css"));
+        assert!(html.contains("jquery.min.js"));
+
+        data.self_contained = Some(true);
+        let mut buf = Vec::new();
+        assert!(write_data(&mut buf, &data).is_ok());
+        let html = String::from_utf8(buf).unwrap();
+        assert!(!html.contains("cdnjs.cloudflare.com"));
+        assert!(html.contains("normalize.css v3.0.3"));
+    }
+
+    #[test]
+    fn test_write_data_search() {
+        let mut data = Data {
+            title: Some(String::from("Test")),
+            contents: Some(vec![Content {
+                label: Some(String::from("Page")),
+                markdown: Some(String::from("Some `</script>` text")),
+                html: Some(String::from("<p>Some text</p>")),
+                ..Content::default()
+            }]),
+            ..Data::default()
+        };
+        let mut buf = Vec::new();
+        assert!(write_data(&mut buf, &data).is_ok());
+        let html = String::from_utf8(buf).unwrap();
+        assert!(!html.contains("search-input"));
+
+        data.search = Some(true);
+        let mut buf = Vec::new();
+        assert!(write_data(&mut buf, &data).is_ok());
+        let html = String::from_utf8(buf).unwrap();
+        assert!(html.contains(r#"<input type="search" id="search-input""#));
+        assert!(html.contains(
+            r#"var index = [{"section":0,"label":"Page","text":"Some <\/script> text"}];"#
+        ));
+    }
+
+    #[test]
+    fn test_write_data_toc() {
+        let mut content = Content {
+            label: Some(String::from("Guide")),
+            markdown: Some(String::from("# Guide\n\n## Install\n\n### From source")),
+            ..Content::default()
+        };
+        let opts = RenderOptions {
+            toc: true,
+            ..RenderOptions::default()
+        };
+        fill_content(&mut content, Path::new("."), &opts).unwrap();
+
+        let data = Data {
+            title: Some(String::from("Test")),
+            contents: Some(vec![content]),
+            ..Data::default()
+        };
+
+        let mut buf = Vec::new();
+        assert!(write_data(&mut buf, &data).is_ok());
+        let html = String::from_utf8(buf).unwrap();
+        assert!(html.contains(r#"<span class="menu-toc" id="menu-toc-0">"#));
+        assert!(html.contains(r##"<a href="#guide-install" class="menu-toc-2">Install</a>"##));
+        assert!(
+            html.contains(r##"<a href="#guide-from-source" class="menu-toc-3">From source</a>"##)
+        );
+        assert!(html.contains(r#"id="guide-from-source""#));
+
+        let pages = render_pages(&data).unwrap();
+        assert!(pages[0]
+            .1
+            .contains(r##"<a href="#guide-install" class="menu-toc-2">"##));
+    }
+
+    #[test]
+    fn test_render_pages() {
+        let data = crate::data::build(Path::new("docs/examples/sections"), None).unwrap();
+        let pages = render_pages(&data).unwrap();
+        let files = pages.iter().map(|(f, _)| f.as_str()).collect::<Vec<&str>>();
+        assert_eq!(
+            files,
+            vec![
+                "index.html",
+                "installation.html",
+                "user-s-guide.html",
+                "api.html",
+                "configuration.html"
+            ]
+        );
+
+        let (_, html) = &pages[2];
+        assert!(html.contains(r#"<a href="user-s-guide.html" id="menu-2" class="is-active">"#));
+        assert!(html.contains(r#"<a href="installation.html" class="float-left">"#));
+        assert!(html.contains(r#"<a href="api.html" class="float-right">"#));
+        assert!(!html.contains("jquery"));
+
+        let contents = vec![
+            Content::new_heading(String::from("Guide")),
+            Content {
+                label: Some(String::from("Index")),
+                html: Some(String::from("<p>1</p>")),
+                ..Content::default()
+            },
+            Content {
+                label: Some(String::from("Index")),
+                html: Some(String::from("<p>2</p>")),
+                ..Content::default()
+            },
+            Content {
+                html: Some(String::from("<p>3</p>")),
+                ..Content::default()
+            },
+        ];
+        assert_eq!(
+            page_files(&contents, true),
+            vec![
+                None,
+                Some(String::from("index-1.html")),
+                Some(String::from("index-2.html")),
+                Some(String::from("page-3.html"))
+            ]
+        );
+    }
+}
@@ -3,6 +3,7 @@ use std::io::BufReader;
 use std::path::PathBuf;
 
 #[test]
+#[allow(clippy::field_reassign_with_default)]
 fn test_data_build() -> Result<(), Box<dyn std::error::Error>> {
     // empty
     let mut root = PathBuf::from("tests");
@@ -36,6 +37,17 @@ fn test_data_build() -> Result<(), Box<dyn std::error::Error>> {
     expected = serde_json::from_reader(reader)?;
     assert_eq!(data, expected);
 
+    // with mdpage.json and initial data overriding it
+    data = mdpage::build(
+        &root,
+        Some(mdpage::Data {
+            title: Some(String::from("Custom title")),
+            ..mdpage::Data::default()
+        }),
+    )?;
+    expected.title = Some(String::from("Custom title"));
+    assert_eq!(data, expected);
+
     // with subdirs and initial data
     root = PathBuf::from("tests/fixtures/data");
     data = mdpage::build(
@@ -83,5 +95,37 @@ fn test_data_build() -> Result<(), Box<dyn std::error::Error>> {
     expected = serde_json::from_reader(reader)?;
     assert_eq!(data, expected);
 
+    // front matter
+    root = PathBuf::from("tests/fixtures/front_matter");
+    data = mdpage::build(&root, None)?;
+    expected_file = File::open("tests/build_expected_front_matter.json")?;
+    reader = BufReader::new(expected_file);
+    expected = serde_json::from_reader(reader)?;
+    assert_eq!(data, expected);
+
     Ok(())
 }
+
+#[test]
+fn test_data_build_errors() {
+    // invalid config
+    let root = PathBuf::from("tests/fixtures/bad_config");
+    match mdpage::build(&root, None) {
+        Err(mdpage::Error::Config { path, .. }) => assert!(path.ends_with("mdpage.json")),
+        res => panic!("expected config error, got: {:?}", res),
+    }
+
+    // missing content file
+    let root = PathBuf::from("tests/fixtures/missing_file");
+    match mdpage::build(&root, None) {
+        Err(mdpage::Error::MissingFile { path, .. }) => assert!(path.ends_with("missing.md")),
+        res => panic!("expected missing file error, got: {:?}", res),
+    }
+
+    // missing root
+    let root = PathBuf::from("tests/fixtures/missing_root");
+    assert!(matches!(
+        mdpage::build(&root, None),
+        Err(mdpage::Error::MissingFile { .. })
+    ));
+}